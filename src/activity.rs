@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use console::Style;
+
+use crate::dates::day;
+use crate::notes::NoteWithId;
+
+/// What happened to a note. Edits are only known from the modified time, so
+/// a note edited several times has one edit, at the last of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Created,
+    Edited,
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::Created => "created",
+            EventKind::Edited => "edited",
+        }
+    }
+
+    pub fn style(self) -> Style {
+        match self {
+            EventKind::Created => Style::new().green(),
+            EventKind::Edited => Style::new().yellow(),
+        }
+    }
+}
+
+/// A note being created or edited, at a stored timestamp.
+#[derive(Clone, Copy)]
+pub struct Event<'a> {
+    pub kind: EventKind,
+    pub time: &'a str,
+    pub note: NoteWithId<'a>,
+}
+
+impl Event<'_> {
+    pub fn day(&self) -> Option<NaiveDate> {
+        day(self.time)
+    }
+}
+
+/// Returns the creations and edits of notes, oldest first. Notes whose
+/// modified time is their created time have not been edited.
+pub fn events<'a, I>(notes: I) -> Vec<Event<'a>>
+where
+    I: IntoIterator<Item = NoteWithId<'a>>,
+{
+    let mut events = vec![];
+
+    for note in notes {
+        events.push(Event {
+            kind: EventKind::Created,
+            time: note.1.created.as_str(),
+            note,
+        });
+
+        if note.modified() != note.created() {
+            events.push(Event {
+                kind: EventKind::Edited,
+                time: note.1.modified.as_str(),
+                note,
+            });
+        }
+    }
+
+    events.sort_by_key(|event| event.time);
+    events
+}
+
+/// Counts events per day. Events whose time cannot be read are left out.
+pub fn daily_counts(events: &[Event<'_>]) -> BTreeMap<NaiveDate, usize> {
+    let mut counts = BTreeMap::new();
+
+    for day in events.iter().filter_map(Event::day) {
+        *counts.entry(day).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/// The shades of the heatmap, from no activity to the busiest days.
+const SHADES: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+
+/// Renders a calendar of a year's daily counts: a column per week starting
+/// on Monday, a row per weekday, and each day shaded by how busy it was
+/// compared to the busiest day of the year.
+pub fn heatmap(counts: &BTreeMap<NaiveDate, usize>, year: i32) -> String {
+    let first = NaiveDate::from_ymd(year, 1, 1);
+    let last = NaiveDate::from_ymd(year, 12, 31);
+    let start = first - Duration::days(first.weekday().num_days_from_monday().into());
+    let weeks = ((last - start).num_days() / 7 + 1) as usize;
+
+    let in_year = counts.range(first..=last);
+    let max = in_year.clone().map(|(_, count)| *count).max().unwrap_or(0);
+    let total: usize = in_year.map(|(_, count)| *count).sum();
+
+    let week_start = |week: usize| start + Duration::weeks(week as i64);
+
+    let mut months = String::from("    ");
+    for week in 0..weeks {
+        let day = week_start(week);
+        let month_starts = (0..7)
+            .map(|d| day + Duration::days(d))
+            .find(|day| day.day() == 1 && day.year() == year);
+
+        if let Some(day) = month_starts {
+            if months.chars().count() <= 4 + week {
+                let padding = 4 + week - months.chars().count();
+                months.push_str(&" ".repeat(padding));
+                months.push_str(&day.format("%b").to_string());
+            }
+        }
+    }
+
+    let mut lines = vec![months.trim_end().to_string()];
+
+    for weekday in 0..7 {
+        let label = match weekday {
+            0 => "Mon ",
+            2 => "Wed ",
+            4 => "Fri ",
+            _ => "    ",
+        };
+        let mut line = String::from(label);
+
+        for week in 0..weeks {
+            let day = week_start(week) + Duration::days(weekday);
+            if day.year() != year {
+                line.push(' ');
+                continue;
+            }
+
+            let count = counts.get(&day).copied().unwrap_or(0);
+            line.push_str(&shade(count, max));
+        }
+
+        lines.push(line.trim_end().to_string());
+    }
+
+    let legend: Vec<String> = (0..SHADES.len()).map(paint_shade).collect();
+    lines.push(String::new());
+    lines.push(format!(
+        "{} creations and edits in {}.    Less {} More",
+        total,
+        year,
+        legend.join("")
+    ));
+
+    lines.join("\n")
+}
+
+/// The shade of a day: blank for no activity, then four levels up to the
+/// busiest day.
+fn shade(count: usize, max: usize) -> String {
+    let level = match count {
+        0 => 0,
+        _ => (count * 4).div_ceil(max).clamp(1, 4),
+    };
+
+    paint_shade(level)
+}
+
+fn paint_shade(level: usize) -> String {
+    let style = match level {
+        0 => Style::new().dim(),
+        _ => Style::new().green(),
+    };
+
+    style.apply_to(SHADES[level]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn count_events() {
+        let mut edited = NoteBuilder::new()
+            .with_time(Local.ymd(2026, 1, 2).and_hms(12, 0, 0))
+            .build();
+        edited.modified = "2026-01-05 09:00:00".to_string();
+        let notes = [
+            edited,
+            NoteBuilder::new()
+                .with_time(Local.ymd(2026, 1, 5).and_hms(8, 0, 0))
+                .build(),
+        ];
+
+        let events = events(notes.iter().enumerate().map(|(i, n)| NoteWithId(i, n)));
+        let kinds: Vec<(usize, EventKind)> = events
+            .iter()
+            .map(|event| (event.note.id(), event.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (0, EventKind::Created),
+                (1, EventKind::Created),
+                (0, EventKind::Edited)
+            ]
+        );
+
+        let counts = daily_counts(&events);
+        assert_eq!(counts.get(&NaiveDate::from_ymd(2026, 1, 2)), Some(&1));
+        assert_eq!(counts.get(&NaiveDate::from_ymd(2026, 1, 5)), Some(&2));
+    }
+
+    #[test]
+    fn render_heatmap() {
+        let mut counts = BTreeMap::new();
+        counts.insert(NaiveDate::from_ymd(2026, 1, 1), 1);
+        counts.insert(NaiveDate::from_ymd(2026, 1, 6), 4);
+        counts.insert(NaiveDate::from_ymd(2025, 12, 31), 9);
+
+        let map = console::strip_ansi_codes(&heatmap(&counts, 2026)).to_string();
+        let lines: Vec<&str> = map.lines().collect();
+
+        // 2026 starts on a Thursday.
+        assert!(lines[0].starts_with("    Jan Feb"));
+        assert!(lines[1].starts_with("Mon  ·"));
+        assert!(lines[2].starts_with("     █"));
+        assert!(lines[4].starts_with("    ░·"));
+        assert_eq!(lines[1].chars().count(), 4 + 53);
+        assert!(lines[9].starts_with("5 creations and edits in 2026."));
+    }
+}
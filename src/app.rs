@@ -1,16 +1,37 @@
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use dirs::data_dir;
 
 use anyhow::{anyhow, Context, Error};
+use chrono::{Datelike, Local, NaiveDate};
 use clap::{clap_app, value_t, App, ArgMatches};
-use dialoguer::{Confirm, Editor};
+use console::Style;
+use dialoguer::{Confirm, Editor, Select};
+use prettytable::{Cell, Row};
 
-use crate::build_table;
+use crate::activity;
+use crate::columns::{notes_table, Column, NoteRow};
+use crate::config::Config;
+use crate::dates::{parse_date, TimeField, TimeFilter};
+use crate::display::{
+    file_size, new_table, print_table, sections, table, terminal_width, CellText,
+};
 use crate::errors::NotesError;
-use crate::notes::{Note, NoteBuilder, Notes};
+use crate::markdown;
+use crate::markers::{strip_markers, Markers};
+use crate::notes::{
+    GroupKey, Listing, Note, NoteBuilder, NoteWithId, Notes, Query, Search, SearchField,
+};
+use crate::output::{self, OutputFormat};
+use crate::pager;
+use crate::registry::TagRegistry;
+use crate::rules::{RulePattern, TagRule};
+use crate::settings::Settings;
 use crate::tags::*;
+use crate::template::Template;
+use crate::theme::ColorChoice;
 use crate::util::*;
 
 pub fn app() -> App<'static, 'static> {
@@ -19,24 +40,97 @@ pub fn app() -> App<'static, 'static> {
         (author: "Liam Woodward <liamowoodward@gmail.com>")
         (about: "Application for storing short notes.")
         (@arg path: --path [notes] "path to the notes file.")
+        (@arg output: -o --output +takes_value +global possible_value[table json ndjson csv tsv] "how to print notes: as a table, or as json, ndjson, csv or tsv for scripts.")
+        (@arg color: --color +takes_value possible_value[auto always never] "when to print colors: auto prints them to a terminal unless NO_COLOR is set; subcommands that print take it too.")
+        (@arg theme: --theme +takes_value +global env("NOTES_THEME") "table styles as key=style settings, such as `id=cyan.bold,time=dim`; the keys are id, time, tag, header and highlight.")
+        (@arg time_format: --("time-format") +takes_value +global env("NOTES_TIME_FORMAT") "how to print times: local, relative such as `3h ago`, iso, or a strftime pattern such as `%d %b %Y`.")
+        (@arg no_pager: --("no-pager") +global "print straight to stdout rather than through the pager; the pager is NOTES_PAGER, PAGER or `less -R`.")
+        (@arg format: --format +takes_value +global conflicts_with[output] "prints each note with a template such as `{id}: {title|truncate(40)} [{tags|join(\" \")}]`, or `@name` for a template saved in the config directory.")
+        (@arg wide: --wide +global visible_alias("no-truncate") "print tables at full width rather than truncating them to fit the terminal.")
+        (@arg columns: --columns +takes_value +global +use_delimiter env("NOTES_COLUMNS") "table columns to print, such as `id,tags,title,modified`; one or more of id, created, modified, tags, title, content, desc, contexts, projects, length, similarity.")
         (@subcommand new =>
          (about: "creates a new note.")
-         (@group new =>
-          (@arg content: * "content of the note")
+         (@group new +required =>
+          (@arg content: "content of the note")
           (@arg file: -f --file [file] "file to create a new note from.")
           (@arg editor: -e --editor [editor] #{0, 1} "create a new note in an editor")
          )
          (@arg tags: --tags +takes_value +multiple "tags to attach to the note.")
          (@arg desc: --desc [desc] "tags to attach to the note.")
+         (@arg strip_markers: --("strip-markers") "remove inline #tag, @context and +project markers from the content.")
         )
         (@subcommand get =>
          (about: "gets one or more notes.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@setting AllowLeadingHyphen)
          (@group get =>
           (@arg all: -a --all "get all notes.")
-          (@arg id: "get the note with the given id.")
+          (@arg query: +multiple +allow_hyphen_values "get the note with the given id, or the notes matching a query such as `tag:rust created>2026-01-01 content~\"deploy\" -tag:done`.")
          )
-         (@arg tags: -t --tags "get notes with the given tags.")
+         (@arg tags: -t --tags +takes_value +use_delimiter "get notes with the given tags.")
          (@arg desc: -d --desc "print note descriptions.")
+         (@arg since: --since +takes_value "only notes from this day on: YYYY-MM-DD, 7d, 2w, yesterday or last-monday.")
+         (@arg until: --until +takes_value "only notes up to and including this day.")
+         (@arg on: --on +takes_value conflicts_with[since until] "only notes from this day.")
+         (@arg modified: -m --modified "filter days by modified time rather than created time.")
+         (@arg sort: --sort +takes_value possible_value[created modified id tag length] "sorts notes by the given field.")
+         (@arg reverse: --reverse "reverses the order of notes.")
+         (@arg limit: -n --limit +takes_value "prints at most this many notes.")
+         (@arg offset: --offset +takes_value "skips this many notes before printing.")
+         (@arg group_by: --("group-by") +takes_value possible_value[tag day week month] conflicts_with[output] "prints notes in sections: one per tag, or per day, week or month they were created in.")
+        )
+        (@subcommand activity =>
+         (about: "prints a calendar heatmap of the notes created and edited in a year.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg year: --year +takes_value "year to print; this year by default.")
+        )
+        (@subcommand timeline =>
+         (about: "prints the notes created and edited, day by day.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg since: --since +takes_value "only days from this one on: YYYY-MM-DD, 7d, 2w, yesterday or last-monday.")
+         (@arg until: --until +takes_value "only days up to and including this one.")
+        )
+        (@subcommand stats =>
+         (about: "prints a summary of the notes: counts, tags, lengths, creation rate and store size.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+        )
+        (@subcommand show =>
+         (about: "shows a note in full, with its content rendered as Markdown.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg id: * "id of the note to show.")
+        )
+        (@subcommand search =>
+         (about: "searches note content, descriptions, titles and tags.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg query: * +multiple "terms to search for.")
+         (@arg in: --in +takes_value +use_delimiter visible_alias[field] possible_value[title tags desc content] "fields to search.")
+         (@arg regex: --regex conflicts_with[fuzzy] "matches the query as a regular expression.")
+         (@arg fuzzy: --fuzzy "matches words within a typo or two of the query.")
+         (@arg desc: -d --desc "print note descriptions.")
+         (@arg since: --since +takes_value "only notes from this day on: YYYY-MM-DD, 7d, 2w, yesterday or last-monday.")
+         (@arg until: --until +takes_value "only notes up to and including this day.")
+         (@arg on: --on +takes_value conflicts_with[since until] "only notes from this day.")
+         (@arg modified: -m --modified "filter days by modified time rather than created time.")
+         (@arg sort: --sort +takes_value possible_value[created modified id tag length] "sorts notes by the given field.")
+         (@arg reverse: --reverse "reverses the order of notes.")
+         (@arg limit: -n --limit +takes_value "prints at most this many notes.")
+         (@arg offset: --offset +takes_value "skips this many notes before printing.")
+        )
+        (@subcommand related =>
+         (about: "lists the notes most similar to a note.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg id: * "id of the note to find related notes for.")
+         (@arg limit: -n --limit +takes_value default_value("5") "prints at most this many notes.")
+         (@arg desc: -d --desc "print note descriptions.")
+        )
+        (@subcommand dedupe =>
+         (about: "finds duplicate notes and asks whether to keep, merge or skip each group.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg threshold: -t --threshold +takes_value default_value("0.8") "how similar notes must be to count as duplicates, from 0 to 1.")
+         (@arg dry_run: --("dry-run") "lists the duplicates without changing anything.")
+        )
+        (@subcommand reindex =>
+         (about: "rebuilds the search index.")
         )
         (@subcommand edit =>
          (about: "edits a note")
@@ -44,63 +138,198 @@ pub fn app() -> App<'static, 'static> {
          (@arg content: -c --content +takes_value "change the note content.")
          (@arg tags: -t --tags +takes_value +multiple "change the note tags.")
          (@arg desc: -d --desc +takes_value "change the note description.")
+         (@arg strip_markers: --("strip-markers") "remove inline #tag, @context and +project markers from the content.")
         )
         (@subcommand delete =>
          (about: "deletes a note")
          (@arg id: * "delete the note with the given id.")
         )
+        (@subcommand tags =>
+         (about: "lists the tags in use.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg registered: -r --registered "list registered tags with their colors and aliases.")
+         (@subcommand set =>
+          (about: "registers a tag with a description, color, or aliases.")
+          (@arg tag: * "tag to register.")
+          (@arg desc: -d --desc +takes_value "description of the tag.")
+          (@arg tag_color: -c --color +takes_value "color to display the tag in.")
+          (@arg aliases: -a --aliases +takes_value +multiple "other names for the tag.")
+         )
+         (@subcommand unset =>
+          (about: "removes a tag from the registry.")
+          (@arg tag: * "tag to remove.")
+         )
+         (@subcommand rename =>
+          (about: "renames a tag on every note.")
+          (@arg old: * "tag to rename.")
+          (@arg new: * "new name of the tag.")
+         )
+         (@subcommand merge =>
+          (about: "merges tags into a single tag on every note.")
+          (@arg tags: * +multiple "tags to merge.")
+          (@arg into: --into +required +takes_value "tag to merge into.")
+         )
+        )
+        (@subcommand config =>
+         (about: "gets, sets, or lists the defaults in the config file; arguments and environment variables override them.")
+         (@subcommand get =>
+          (about: "prints a setting.")
+          (@arg key: * "setting to print: path, editor, columns, time-format, color, theme, pager, sort, confirm or tags.")
+         )
+         (@subcommand set =>
+          (about: "changes a setting; an empty value removes it.")
+          (@arg key: * "setting to change.")
+          (@arg value: * "new value; columns and tags are comma separated.")
+         )
+         (@subcommand list =>
+          (about: "lists the settings in the config file.")
+         )
+        )
+        (@subcommand rules =>
+         (about: "lists the auto-tagging rules.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@subcommand add =>
+          (about: "adds a rule that tags matching notes.")
+          (@group pattern +required =>
+           (@arg content: -c --content +takes_value "regular expression to match against note content.")
+           (@arg ext: -e --ext +takes_value "extension of the file a note is created from.")
+          )
+          (@arg tag: * "tag to add to matching notes.")
+         )
+         (@subcommand delete =>
+          (about: "deletes a rule.")
+          (@arg id: * "delete the rule with the given id.")
+         )
+        )
+        (@subcommand query =>
+         (about: "lists saved queries and how many notes each matches.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@subcommand list =>
+          (about: "lists saved queries and how many notes each matches.")
+         )
+         (@subcommand save =>
+          (about: "saves a query under a name.")
+          (@setting TrailingVarArg)
+          (@arg name: * "name to save the query as.")
+          (@arg expr: * +multiple +allow_hyphen_values "query to save, such as `tag:oncall created>7d -tag:done`.")
+         )
+         (@subcommand run =>
+          (about: "gets the notes matching a saved query.")
+          (@arg name: * "name of the saved query.")
+          (@arg desc: -d --desc "print note descriptions.")
+         )
+         (@subcommand delete =>
+          (about: "deletes a saved query.")
+          (@arg name: * "name of the saved query.")
+         )
+        )
+        (@subcommand retag =>
+         (about: "re-applies the auto-tagging rules to all notes.")
+         (@arg color: --color +takes_value possible_value[auto always never] "when to print colors.")
+         (@arg dry_run: --("dry-run") "show the changes without saving them.")
+        )
     )
 }
 
-macro_rules! print_notes {
-    ([$notes:expr], $err:expr) => {{
-        print_notes!([$notes], $err, [(id, "b"), (created, "b"), (tags, ""), (content, "")])
-    }};
-    ([$notes:expr], $err:expr, $(($name:ident, $style:expr)),+) => {{
-        print_notes!([$notes], $err, [(id, "b"), (created, "b"), (tags, ""), (content, ""), $(($name, $style)),+])
-    }};
-    ([$notes:expr], $err:expr, [$(($name:ident, $style:expr)),+]) => {{
-        if let Some(notes) = $notes {
-            let table = build_table!(
-                vec![notes],
-                [$(($name, $style)),+]
-            );
-            table.printstd();
-        } else {
-            println!("{}", $err);
-        }
-    }};
-    ($notes:expr, $err:expr) => {{
-        print_notes!($notes, $err, [(id, "b"), (created, "b"), (tags, ""), (content, "")])
-    }};
-    ($notes:expr, $err:expr, $(($name:ident, $style:expr)),+) => {{
-        print_notes!($notes, $err, [(id, "b"), (created, "b"), (tags, ""), (content, ""), $(($name, $style)),+])
-    }};
-    ($notes:expr, $err:expr, [$(($name:ident, $style:expr)),+]) => {{
-        if let Some(notes) = $notes {
-            let table = build_table!(
-                notes,
-                [$(($name, $style)),+]
-            );
-            table.printstd();
-        } else {
-            println!("{}", $err);
+/// Prints notes in the way given by the listing arguments: with a `--format`
+/// template, in the `--output` format, or as a table of the `--columns`,
+/// falling back to the default columns of the listing. Prints `empty`
+/// instead of a table if there are no notes.
+fn print_notes<'a, T: NoteRow<'a>>(
+    args: &ArgMatches<'_>,
+    registry: &TagRegistry,
+    notes: Option<Vec<T>>,
+    empty: &str,
+    default_columns: &[Column],
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let output = output_format(args)?;
+
+    if let Some(template) = args.value_of("format") {
+        let template = Template::load(template)?;
+        let lines: Vec<String> = notes
+            .iter()
+            .flatten()
+            .map(|note| template.render(&note.as_note_with_id(), &settings.time_format))
+            .collect();
+
+        if !lines.is_empty() {
+            pager::page(&lines.join("\n"), settings)?;
         }
-    }};
+    } else if let Some(format) = output.data() {
+        output::print_notes(
+            format,
+            notes.iter().flatten().map(|note| note.as_note_with_id()),
+        )?;
+    } else if let Some(notes) = notes {
+        let columns = columns(args, default_columns, settings)?;
+        let table = notes_table(&notes, &columns, registry, table_width(args), settings);
+        print_table(&table, settings)?;
+    } else {
+        println!("{}", empty);
+    }
+
+    Ok(())
 }
 
 /// Runs the application.
-pub fn run_app(app: App) -> anyhow::Result<()> {
-    let matches = app.get_matches();
-    let path = matches.value_of("path");
+pub fn run_app(matches: &ArgMatches<'_>) -> anyhow::Result<()> {
+    // `config` runs before the settings are resolved, so that a value that
+    // does not parse, such as a misspelled color, can still be set with it.
+    // A file that is not valid TOML or has unknown keys has to be fixed by
+    // hand.
+    if let ("config", Some(args)) = matches.subcommand() {
+        return run_config(args);
+    }
+
+    let config = match Config::path().map(Config::from_file) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("{}\nUsing the default settings.", e);
+            Config::default()
+        }
+        None => Config::default(),
+    };
+    let settings = &settings(matches, config)?;
+
+    let path = matches
+        .value_of("path")
+        .map(PathBuf::from)
+        .or_else(|| settings.config.notes_path());
+    let path = path.as_deref();
     let mut notes = get_notes_from_file(path)?;
 
+    // Commands that only read the notes return without writing them back.
     match matches.subcommand() {
-        ("new", Some(new)) => run_new_note(&mut notes, new)?,
-        ("get", Some(get)) => run_get_note(&notes, get)?,
+        ("new", Some(new)) => run_new_note(&mut notes, new, settings)?,
+        ("get", Some(get)) => return run_get_note(&notes, get, settings),
+        ("show", Some(show)) => return run_show(&notes, show, settings),
+        ("stats", Some(stats)) => return run_stats(&notes, stats, path, settings),
+        ("activity", Some(activity)) => return run_activity(&notes, activity, settings),
+        ("timeline", Some(timeline)) => return run_timeline(&notes, timeline, settings),
+        ("search", Some(search)) => return run_search(&notes, search, settings),
+        ("related", Some(related)) => return run_related(&notes, related, settings),
+        ("dedupe", Some(dedupe)) => run_dedupe(&mut notes, dedupe, settings)?,
+        ("reindex", Some(_)) => {
+            let count = notes.reindex();
+            println!("Search index rebuilt with {} note(s).", count);
+        }
         ("edit", Some(edit)) => run_edit_note(&mut notes, edit)?,
-        ("delete", Some(delete)) => run_delete_note(&mut notes, delete)?,
-        _ => print_notes!(notes.get_all_with_id(), "There are no notes."),
+        ("delete", Some(delete)) => run_delete_note(&mut notes, delete, settings)?,
+        ("tags", Some(tags)) => run_tags(&mut notes, tags, settings)?,
+        ("rules", Some(rules)) => run_rules(&mut notes, rules, settings)?,
+        ("query", Some(query)) => run_query(&mut notes, query, settings)?,
+        ("retag", Some(retag)) => run_retag(&mut notes, retag, settings)?,
+        _ => {
+            return print_notes(
+                matches,
+                notes.registry(),
+                notes.get_all_with_id(),
+                "There are no notes.",
+                &Column::DEFAULT,
+                settings,
+            )
+        }
     }
 
     save_notes_to_file(&notes, path)?;
@@ -160,16 +389,19 @@ fn save_notes_to_file<P: AsRef<Path>>(notes: &Notes, path: Option<P>) -> anyhow:
 }
 
 /// Creates a new note with valid user-supplied parameters.
-fn run_new_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Result<()> {
-    let note = {
+fn run_new_note<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let mut note = {
         if let Some(path) = args.value_of("file") {
             new_note_from_file(path)?
         } else if args.is_present("editor") {
-            new_note_from_editor(args.value_of("editor"))?
+            new_note_from_editor(editor(args, settings).as_deref())?
         } else {
             let mut builder = NoteBuilder::new().with_content(
-                &args
-                    .value_of("content")
+                args.value_of("content")
                     .ok_or(anyhow!("Missing note content."))?,
             );
 
@@ -185,60 +417,603 @@ fn run_new_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Result<
         }
     };
 
+    if args.values_of("tags").is_none() {
+        if let Some(tags) = &settings.config.tags {
+            note.tags = Tags::from(tags.clone());
+        }
+    }
+
+    let markers = Markers::parse(&note.content);
+    note.add_markers(&markers);
+
+    if args.is_present("strip_markers") {
+        note.content = strip_markers(&note.content);
+    }
+
+    let added = notes.apply_rules(&mut note, args.value_of("file").map(Path::new));
     let id = notes.push(note);
 
     println!("Note with ID {} created.", id);
 
+    if !added.0.is_empty() {
+        println!("Tags `{}` added by auto-tagging rules.", added);
+    }
+
     Ok(())
 }
 
-/// Processes a user query for note(s) and prints it to stdout.
-fn run_get_note<'a>(notes: &Notes, args: &ArgMatches<'a>) -> anyhow::Result<()> {
-    let desc = args.is_present("desc");
-    let mut all = args.is_present("all");
-    let tags = args.values_of("tags");
-    let id = args.value_of("id");
+/// Processes a user query for note(s) and prints it to stdout. A lone
+/// number is taken as a note id, and a leading `@name` runs a saved query;
+/// anything else is parsed as a query.
+fn run_get_note<'a>(
+    notes: &Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let terms: Vec<&str> = args.values_of("query").into_iter().flatten().collect();
 
-    // If `id` is not present, okay for it to be none, as we should never use it. It goes down
-    // the `all` branch.
-    if id.is_none() {
-        all = true;
+    // `get` takes terms that start with `-`, so misspelled flags end up here.
+    if let Some(term) = terms.iter().find(|term| term.starts_with("--")) {
+        return Err(Error::new(NotesError::UnexpectedArgument(term.to_string())));
     }
 
-    if all {
-        if desc {
-            print_notes!(notes.get_all_with_id(), "There are no notes.", (desc, ""));
-        } else {
-            print_notes!(notes.get_all_with_id(), "There are no notes.");
+    let query = terms.join(" ");
+
+    let mut query = match query.parse::<usize>() {
+        Ok(id) => Query::new().id(id),
+        Err(_) => Query::parse(&notes.queries().expand(&query)?)?,
+    };
+
+    if let Some(tags) = args.values_of("tags") {
+        query = query.tags(&Tags::from(tags.collect::<Vec<&str>>()));
+    }
+    query = query.time(&time_filter(args)?);
+
+    print_query(notes, &query, args, settings)
+}
+
+/// Shows a note as a document: a header with its metadata, then its content
+/// rendered as Markdown. Templates and machine-readable output print it the
+/// same as `get`.
+fn run_show<'a>(notes: &Notes, args: &ArgMatches<'a>, settings: &Settings) -> anyhow::Result<()> {
+    let id = value_t!(args, "id", usize).context("Missing note ID.")?;
+    let note = notes
+        .get_with_id(id)
+        .ok_or_else(|| Error::new(NotesError::InvalidNoteId(id)))?;
+
+    if args.is_present("format") || !output_format(args)?.is_table() {
+        return print_notes(args, notes.registry(), Some(vec![note]), "", &[], settings);
+    }
+
+    let width = terminal_width().unwrap_or(80).min(80);
+    let label = Style::new().bold();
+    let mut doc = format!("{}\n", settings.theme.id.apply_to(format!("Note {}", id)));
+
+    let fields = [
+        ("Created", settings.display_time(note.created())),
+        ("Modified", settings.display_time(note.modified())),
+        (
+            "Tags",
+            note.tags().cell_text(notes.registry(), &settings.theme),
+        ),
+        ("Contexts", note.contexts().join(",")),
+        ("Projects", note.projects().join(",")),
+        ("Desc", note.desc().clone()),
+    ];
+    for (name, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        doc.push_str(&format!(
+            "{} {}\n",
+            label.apply_to(format!("{:<8}", name)),
+            value
+        ));
+    }
+
+    doc.push_str(&format!(
+        "{}\n\n",
+        Style::new().dim().apply_to("─".repeat(width))
+    ));
+    doc.push_str(&markdown::render(note.content(), width));
+
+    pager::page(&doc, settings)
+}
+
+/// Prints the notes matching a query, ordered and paged by the listing
+/// arguments.
+fn print_query<'a>(
+    notes: &Notes,
+    query: &Query,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let mut listing = listing(args)?;
+    if listing.sort.is_none() {
+        listing.sort = settings
+            .config
+            .sort
+            .as_deref()
+            .map(str::parse)
+            .transpose()?;
+    }
+    let empty = if query.is_empty() {
+        "There are no notes."
+    } else {
+        "No notes found."
+    };
+
+    if let Some(key) = args.value_of("group_by") {
+        let notes_found = listing.collect(notes.query(query));
+        return print_groups(
+            args,
+            notes.registry(),
+            notes_found,
+            empty,
+            key.parse()?,
+            settings,
+        );
+    }
+
+    print_notes(
+        args,
+        notes.registry(),
+        listing.collect(notes.query(query)),
+        empty,
+        &Column::DEFAULT,
+        settings,
+    )
+}
+
+/// Prints a summary of the notes and of the tags they use, most used first.
+fn run_stats<'a>(
+    notes: &Notes,
+    args: &ArgMatches<'a>,
+    path: Option<&Path>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => get_xdg_data_dir()?,
+    };
+    let stats = notes.stats(&path, Local::today().naive_local());
+
+    let output = output_format(args)?;
+    if !output.is_table() {
+        return output::print_value(output, "stats", &stats);
+    }
+
+    let longest = stats.longest.as_ref().map_or(String::new(), |longest| {
+        format!("note {} ({} chars)", longest.id, longest.length)
+    });
+    let summary = [
+        ("Notes", stats.notes.to_string()),
+        ("Untagged", stats.untagged.to_string()),
+        (
+            "Average length",
+            format!("{:.0} chars", stats.average_length),
+        ),
+        ("Longest", longest),
+        ("Per week", format!("{:.1}", stats.notes_per_week)),
+        ("Store size", file_size(stats.store_size)),
+    ];
+
+    let mut summary_table = new_table();
+    for (name, value) in summary.iter() {
+        summary_table.add_row(Row::new(vec![
+            Cell::new(&Style::new().bold().apply_to(name).to_string()),
+            Cell::new(value),
+        ]));
+    }
+    let mut text = summary_table.to_string();
+
+    if !stats.tags.is_empty() {
+        let rows = stats
+            .tags
+            .iter()
+            .map(|count| vec![count.tag().clone(), count.notes().to_string()])
+            .collect();
+        let tags = table(
+            &[("Tag", Style::new().bold()), ("Notes", Style::new())],
+            rows,
+            &settings.theme,
+        );
+        text.push('\n');
+        text.push_str(&tags.to_string());
+    }
+
+    pager::page(text.trim_end_matches('\n'), settings)
+}
+
+/// Prints a heatmap of the notes created and edited each day of a year.
+fn run_activity<'a>(
+    notes: &Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let year = args
+        .value_of("year")
+        .map(str::parse)
+        .transpose()
+        .context("The year must be a number.")?
+        .unwrap_or_else(|| Local::today().year());
+
+    // The heatmap pads the year with the days of its first and last weeks.
+    let padded = |year: Option<i32>, month, day| {
+        year.and_then(|year| NaiveDate::from_ymd_opt(year, month, day))
+    };
+    if padded(year.checked_sub(1), 12, 25).is_none() || padded(year.checked_add(1), 1, 7).is_none()
+    {
+        return Err(Error::new(NotesError::InvalidYear(year)));
+    }
+
+    let events = activity::events(notes.iter_with_id());
+    let heatmap = activity::heatmap(&activity::daily_counts(&events), year);
+    pager::page(&heatmap, settings)
+}
+
+/// Prints the notes created and edited, oldest first, in a section per day.
+fn run_timeline<'a>(
+    notes: &Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let today = Local::today().naive_local();
+    let since = args
+        .value_of("since")
+        .map(|s| parse_date(s, today))
+        .transpose()?;
+    let until = args
+        .value_of("until")
+        .map(|s| parse_date(s, today))
+        .transpose()?;
+
+    let events = activity::events(notes.iter_with_id());
+    let mut days: Vec<(NaiveDate, Vec<activity::Event<'_>>)> = vec![];
+
+    for event in events {
+        let day = match event.day() {
+            Some(day) => day,
+            None => continue,
+        };
+        if since.is_some_and(|since| day < since) || until.is_some_and(|until| day > until) {
+            continue;
         }
-    } else if let Some(tags) = tags {
-        if desc {
-            print_notes!(
-                notes.get_all_with_tags(Tags::from(tags.collect::<Vec<&str>>())),
-                "There are no notes.",
-                (desc, "")
-            );
-        } else {
-            print_notes!(
-                notes.get_all_with_tags(Tags::from(tags.collect::<Vec<&str>>())),
-                "There are no notes."
-            );
+
+        match days.last_mut() {
+            Some((last, events)) if *last == day => events.push(event),
+            _ => days.push((day, vec![event])),
         }
-    } else if desc {
-        // Safe unwrap as we check if `id` is `None` above.
-        print_notes!(
-            [notes.get_with_id(id.unwrap().parse::<usize>()?)],
-            "No note found.",
-            (desc, "")
-        );
+    }
+
+    if days.is_empty() {
+        println!("No activity found.");
+        return Ok(());
+    }
+
+    let days = days.into_iter().map(|(day, events)| {
+        let lines: Vec<String> = events
+            .iter()
+            .map(|event| {
+                format!(
+                    "{} {} {} {}",
+                    event.time.get(11..16).unwrap_or(""),
+                    event
+                        .kind
+                        .style()
+                        .apply_to(format!("{:<7}", event.kind.name())),
+                    settings.theme.id.apply_to(event.note.id()),
+                    event.note.title()
+                )
+            })
+            .collect();
+        (
+            day.format("%a %Y-%m-%d").to_string(),
+            events.len(),
+            lines.join("\n"),
+        )
+    });
+
+    pager::page(&sections(days, &settings.theme), settings)
+}
+
+/// Prints notes in sections, one per group given by `--group-by`, each as a
+/// table or with the `--format` template. Notes are listed in each group
+/// they are in.
+fn print_groups(
+    args: &ArgMatches<'_>,
+    registry: &TagRegistry,
+    notes: Option<Vec<NoteWithId<'_>>>,
+    empty: &str,
+    key: GroupKey,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let template = args.value_of("format").map(Template::load).transpose()?;
+    let notes = match notes {
+        Some(notes) => notes,
+        None if template.is_some() => return Ok(()),
+        None => {
+            println!("{}", empty);
+            return Ok(());
+        }
+    };
+    let columns = columns(args, &Column::DEFAULT, settings)?;
+
+    let groups = key.group(notes).into_iter().map(|(name, notes)| {
+        let body = match &template {
+            Some(template) => notes
+                .iter()
+                .map(|note| template.render(note, &settings.time_format))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            None => notes_table(&notes, &columns, registry, table_width(args), settings)
+                .to_string()
+                .trim_end_matches('\n')
+                .to_string(),
+        };
+        (name, notes.len(), body)
+    });
+
+    pager::page(&sections(groups, &settings.theme), settings)
+}
+
+/// Returns the `--color` given to the command or to the subcommand that is
+/// run, which takes precedence. It is not global, so that `tags set` can take
+/// the color of a tag as `--color`.
+fn color_arg<'a>(matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    let mut value = matches.value_of("color");
+    let mut args = matches;
+
+    while let (_, Some(sub)) = args.subcommand() {
+        value = sub.value_of("color").or(value);
+        args = sub;
+    }
+
+    value
+}
+
+/// Resolves the settings of the run from the arguments, falling back to the
+/// config file, and enables colors as given by `--color`.
+fn settings(args: &ArgMatches<'_>, config: Config) -> anyhow::Result<Settings> {
+    color_arg(args)
+        .or(config.color.as_deref())
+        .map_or(Ok(ColorChoice::Auto), str::parse::<ColorChoice>)?
+        .apply();
+
+    let theme = args.value_of("theme").or(config.theme.as_deref());
+    let time_format = args
+        .value_of("time_format")
+        .or(config.time_format.as_deref());
+
+    Ok(Settings {
+        theme: theme.map(str::parse).transpose()?.unwrap_or_default(),
+        time_format: time_format.map(str::parse).transpose()?.unwrap_or_default(),
+        paging: !args.is_present("no_pager"),
+        config,
+    })
+}
+
+/// Returns the output format given by the global `--output` argument.
+pub fn output_format(args: &ArgMatches<'_>) -> anyhow::Result<OutputFormat> {
+    args.value_of("output")
+        .map_or(Ok(OutputFormat::Table), str::parse)
+}
+
+/// Returns the width to fit tables to: the terminal width, unless `--wide`
+/// is given.
+fn table_width(args: &ArgMatches<'_>) -> Option<usize> {
+    if args.is_present("wide") {
+        None
     } else {
-        // Safe unwrap as we check if `id` is `None` above.
-        print_notes!(
-            [notes.get_with_id(id.unwrap().parse::<usize>()?)],
-            "No note found."
+        terminal_width()
+    }
+}
+
+/// Returns the table columns given by the global `--columns` argument, or the
+/// default columns for the listing. `--desc` adds the description column.
+fn columns(
+    args: &ArgMatches<'_>,
+    default: &[Column],
+    settings: &Settings,
+) -> anyhow::Result<Vec<Column>> {
+    let mut columns = match (args.values_of("columns"), &settings.config.columns) {
+        (Some(names), _) => names
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<Column>>>()?,
+        (None, Some(names)) if default == Column::DEFAULT => names
+            .iter()
+            .map(|name| name.parse())
+            .collect::<anyhow::Result<Vec<Column>>>()?,
+        (None, _) => default.to_vec(),
+    };
+
+    if args.is_present("desc") && !columns.contains(&Column::Desc) {
+        columns.push(Column::Desc);
+    }
+
+    Ok(columns)
+}
+
+/// Builds the time filter given by the `--since`, `--until`, `--on` and
+/// `--modified` arguments.
+fn time_filter(args: &ArgMatches<'_>) -> anyhow::Result<TimeFilter> {
+    let field = if args.is_present("modified") {
+        TimeField::Modified
+    } else {
+        TimeField::Created
+    };
+
+    TimeFilter::parse(
+        field,
+        args.value_of("since"),
+        args.value_of("until"),
+        args.value_of("on"),
+    )
+}
+
+/// Builds the ordering and paging given by the `--sort`, `--reverse`,
+/// `--limit` and `--offset` arguments.
+fn listing(args: &ArgMatches<'_>) -> anyhow::Result<Listing> {
+    Ok(Listing {
+        sort: args.value_of("sort").map(str::parse).transpose()?,
+        reverse: args.is_present("reverse"),
+        limit: args
+            .value_of("limit")
+            .map(str::parse)
+            .transpose()
+            .context("The limit must be a number.")?,
+        offset: args
+            .value_of("offset")
+            .map_or(Ok(0), str::parse)
+            .context("The offset must be a number.")?,
+    })
+}
+
+/// Searches notes and prints the matches, most relevant first.
+fn run_search<'a>(notes: &Notes, args: &ArgMatches<'a>, settings: &Settings) -> anyhow::Result<()> {
+    let query = args
+        .values_of("query")
+        .context("Missing search query.")?
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let mut search = if args.is_present("regex") {
+        Search::regex(&query)?
+    } else if args.is_present("fuzzy") {
+        Search::fuzzy(&query)
+    } else {
+        Search::new(&query)
+    };
+
+    search = search.with_time(time_filter(args)?);
+
+    if search.is_indexable() && !notes.is_indexed() {
+        eprintln!("The search index is missing or stale; run `notes reindex` to rebuild it.");
+    }
+
+    if let Some(fields) = args.values_of("in") {
+        search = search.with_fields(
+            fields
+                .map(str::parse)
+                .collect::<anyhow::Result<Vec<SearchField>>>()?,
         );
     }
 
+    print_notes(
+        args,
+        notes.registry(),
+        listing(args)?.collect(notes.search(&search).into_iter().flatten()),
+        "No notes found.",
+        &Column::DEFAULT,
+        settings,
+    )
+}
+
+/// Prints the notes most similar to a note, most similar first.
+fn run_related<'a>(
+    notes: &Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let id = value_t!(args, "id", usize).context("Missing note ID.")?;
+    let related = listing(args)?.collect(notes.related(id)?.into_iter().flatten());
+
+    print_notes(
+        args,
+        notes.registry(),
+        related,
+        "No related notes found.",
+        &[
+            Column::Id,
+            Column::Similarity,
+            Column::Created,
+            Column::Tags,
+            Column::Content,
+        ],
+        settings,
+    )
+}
+
+/// Walks through each group of duplicate notes, asking whether to keep the
+/// first note and delete the rest, merge them into one, or skip the group.
+fn run_dedupe<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let threshold = value_t!(args, "threshold", f64)
+        .ok()
+        .filter(|t| (0.0..=1.0).contains(t))
+        .context("The threshold must be a number from 0 to 1.")?;
+    let dry_run = args.is_present("dry_run");
+
+    if !dry_run && !console::user_attended() {
+        return Err(anyhow!(
+            "Deduplicating needs a terminal to prompt in; use `--dry-run` to list duplicates."
+        ));
+    }
+
+    let groups: Vec<Vec<usize>> = match notes.get_duplicates(threshold) {
+        Some(groups) => groups
+            .iter()
+            .map(|group| group.iter().map(NoteWithId::id).collect())
+            .collect(),
+        None => {
+            println!("No duplicate notes found.");
+            return Ok(());
+        }
+    };
+
+    // IDs of the notes deleted so far, before any deletions, so that the IDs
+    // of later groups can be moved down.
+    let mut deleted: Vec<usize> = vec![];
+
+    for (i, group) in groups.iter().enumerate() {
+        let ids: Vec<usize> = group
+            .iter()
+            .map(|id| id - deleted.iter().filter(|d| *d < id).count())
+            .collect();
+
+        println!("Duplicate group {} of {}:", i + 1, groups.len());
+        let duplicates: Vec<NoteWithId<'_>> =
+            ids.iter().filter_map(|id| notes.get_with_id(*id)).collect();
+        let table = notes_table(
+            &duplicates,
+            &columns(args, &Column::DEFAULT, settings)?,
+            notes.registry(),
+            table_width(args),
+            settings,
+        );
+        print_table(&table, settings)?;
+
+        if dry_run {
+            continue;
+        }
+
+        let choice = Select::new()
+            .with_prompt("Keep the first note, merge them into one, or skip?")
+            .items(&["keep", "merge", "skip"])
+            .default(2)
+            .interact()?;
+
+        match choice {
+            0 => {
+                for id in ids[1..].iter().rev() {
+                    notes.delete(*id)?;
+                }
+                println!(
+                    "Note {} kept; {} duplicate(s) deleted.",
+                    ids[0],
+                    ids.len() - 1
+                );
+            }
+            1 => {
+                let id = notes.merge(&ids)?;
+                println!("{} notes merged into note {}.", ids.len(), id);
+            }
+            _ => continue,
+        }
+
+        deleted.extend_from_slice(&group[1..]);
+    }
+
     Ok(())
 }
 
@@ -250,14 +1025,26 @@ fn run_edit_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Result
         .map(|t| Tags::from(t.collect::<Vec<&str>>()));
     let desc = args.value_of("desc").map(|s| s.to_string());
 
-    let new_note = notes.edit(id, content, tags, desc)?;
+    let markers = content.as_deref().map(Markers::parse);
+    let content = if args.is_present("strip_markers") {
+        content.map(|c| strip_markers(&c))
+    } else {
+        content
+    };
+
+    notes.edit(id, content, tags, desc)?;
+    let new_note = notes.add_markers(id, &markers.unwrap_or_default())?;
 
     println!("Note {} edited: {}", id, new_note.content);
 
     Ok(())
 }
 
-fn run_delete_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Result<()> {
+fn run_delete_note<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
     // Make sure the note exists and get its content to print
     // the confirmation prompt.
     let id = value_t!(args, "id", usize).context("Missing note ID to delete.")?;
@@ -271,7 +1058,7 @@ fn run_delete_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Resu
 
     let confirm = format!("Are you sure that you want to delete `{}: {}`", id, content);
 
-    if Confirm::new().with_prompt(confirm).interact()? {
+    if !settings.config.confirm.unwrap_or(true) || Confirm::new().with_prompt(confirm).interact()? {
         notes.delete(id)?;
         println!("Note `{}: {}` deleted.", id, content);
     }
@@ -279,6 +1066,370 @@ fn run_delete_note<'a>(notes: &mut Notes, args: &ArgMatches<'a>) -> anyhow::Resu
     Ok(())
 }
 
+/// Gets, sets, or lists the settings in the config file.
+fn run_config<'a>(args: &ArgMatches<'a>) -> anyhow::Result<()> {
+    let path = Config::path().context("Unable to access config directory.")?;
+    let mut config = Config::from_file(&path)?;
+
+    match args.subcommand() {
+        ("get", Some(get)) => {
+            let key = get.value_of("key").context("Missing config key.")?;
+
+            match config.get(key)? {
+                Some(value) => println!("{}", value),
+                None => println!("`{}` is not set.", key),
+            }
+        }
+        ("set", Some(set)) => {
+            let key = set.value_of("key").context("Missing config key.")?;
+            let value = set.value_of("value").context("Missing config value.")?;
+
+            config.set(key, value)?;
+            config.to_file(&path)?;
+
+            match config.get(key)? {
+                Some(value) => println!("`{}` set to `{}`.", key, value),
+                None => println!("`{}` removed.", key),
+            }
+        }
+        _ => {
+            let settings = config.settings();
+
+            if settings.is_empty() {
+                println!("There are no settings in `{}`.", path.display());
+                return Ok(());
+            }
+
+            let mut table = new_table();
+            for (key, value) in settings.iter() {
+                table.add_row(Row::new(vec![
+                    Cell::new(&Style::new().bold().apply_to(key).to_string()),
+                    Cell::new(value),
+                ]));
+            }
+            let display = Settings {
+                config,
+                paging: !args.is_present("no_pager"),
+                ..Settings::default()
+            };
+            print_table(&table, &display)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists, renames, merges, or registers tags.
+fn run_tags<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    match args.subcommand() {
+        ("rename", Some(rename)) => {
+            let old = rename.value_of("old").context("Missing tag to rename.")?;
+            let new = rename.value_of("new").context("Missing new tag name.")?;
+
+            let changed = notes.rename_tag(Tag::from(old), Tag::from(new))?;
+
+            println!("Tag `{}` renamed to `{}` on {} note(s).", old, new, changed);
+        }
+        ("merge", Some(merge)) => {
+            let tags = merge
+                .values_of("tags")
+                .map(|t| Tags::from(t.collect::<Vec<&str>>()))
+                .context("Missing tags to merge.")?;
+            let into = merge
+                .value_of("into")
+                .context("Missing tag to merge into.")?;
+
+            let changed = notes.merge_tags(tags.clone(), Tag::from(into))?;
+
+            println!(
+                "Tags `{}` merged into `{}` on {} note(s).",
+                tags, into, changed
+            );
+        }
+        ("set", Some(set)) => {
+            let tag = Tag::from(set.value_of("tag").context("Missing tag to register.")?);
+            let color = set.value_of("tag_color").map(str::parse).transpose()?;
+
+            if let Some(aliases) = set.values_of("aliases") {
+                for alias in aliases {
+                    notes
+                        .registry_mut()
+                        .add_alias(tag.clone(), Tag::from(alias))?;
+                }
+            }
+
+            let record = notes.registry_mut().get_or_insert(tag.clone());
+
+            if let Some(desc) = set.value_of("desc") {
+                record.desc = desc.to_string();
+            }
+
+            if color.is_some() {
+                record.color = color;
+            }
+
+            println!("Tag `{}` registered.", tag);
+        }
+        ("unset", Some(unset)) => {
+            let tag = Tag::from(unset.value_of("tag").context("Missing tag to remove.")?);
+
+            if notes.registry_mut().remove(&tag).is_some() {
+                println!("Tag `{}` removed from the registry.", tag);
+            } else {
+                println!("Tag `{}` is not registered.", tag);
+            }
+        }
+        _ if args.is_present("registered") => {
+            if let Some(records) = notes.registry().get_all() {
+                let registry = notes.registry();
+                let rows = records
+                    .iter()
+                    .map(|record| {
+                        vec![
+                            record.tag().cell_text(registry, &settings.theme),
+                            record.color(),
+                            record.aliases().cell_text(registry, &settings.theme),
+                            record.desc().clone(),
+                        ]
+                    })
+                    .collect();
+
+                print_table(
+                    &table(
+                        &[
+                            ("Tag", Style::new().bold()),
+                            ("Color", Style::new()),
+                            ("Aliases", Style::new()),
+                            ("Desc", Style::new()),
+                        ],
+                        rows,
+                        &settings.theme,
+                    ),
+                    settings,
+                )?;
+            } else {
+                println!("There are no registered tags.");
+            }
+        }
+        _ => {
+            if let Some(usage) = notes.get_tag_usage() {
+                let registry = notes.registry();
+                let rows = usage
+                    .iter()
+                    .map(|usage| {
+                        vec![
+                            usage.tag().cell_text(registry, &settings.theme),
+                            usage.notes().to_string(),
+                            settings.display_time(usage.last_used()),
+                            usage.desc().clone(),
+                        ]
+                    })
+                    .collect();
+
+                print_table(
+                    &table(
+                        &[
+                            ("Tag", Style::new().bold()),
+                            ("Notes", Style::new()),
+                            ("Last Used", Style::new()),
+                            ("Desc", Style::new()),
+                        ],
+                        rows,
+                        &settings.theme,
+                    ),
+                    settings,
+                )?;
+            } else {
+                println!("There are no tags.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists, adds, or deletes auto-tagging rules.
+fn run_rules<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    match args.subcommand() {
+        ("add", Some(add)) => {
+            let pattern = if let Some(content) = add.value_of("content") {
+                RulePattern::content(content)?
+            } else {
+                RulePattern::FileExtension(
+                    add.value_of("ext")
+                        .context("Missing pattern for the rule.")?
+                        .to_string(),
+                )
+            };
+            let tag = Tag::from(add.value_of("tag").context("Missing tag for the rule.")?);
+
+            let id = notes.rules_mut().push(TagRule { pattern, tag });
+
+            println!("Rule with ID {} created.", id);
+        }
+        ("delete", Some(delete)) => {
+            let id = value_t!(delete, "id", usize).context("Missing rule ID to delete.")?;
+
+            let rule = notes.rules_mut().delete(id)?;
+
+            println!("Rule `{}: {} -> {}` deleted.", id, rule.pattern, rule.tag);
+        }
+        _ => {
+            if let Some(rules) = notes.rules().get_all_with_id() {
+                let rows = rules
+                    .iter()
+                    .map(|rule| {
+                        vec![
+                            rule.id().to_string(),
+                            rule.pattern(),
+                            rule.tag().cell_text(notes.registry(), &settings.theme),
+                        ]
+                    })
+                    .collect();
+
+                print_table(
+                    &table(
+                        &[
+                            ("Id", Style::new().bold()),
+                            ("Pattern", Style::new()),
+                            ("Tag", Style::new()),
+                        ],
+                        rows,
+                        &settings.theme,
+                    ),
+                    settings,
+                )?;
+            } else {
+                println!("There are no rules.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Saves, runs, lists, or prints saved queries.
+fn run_query<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    match args.subcommand() {
+        ("save", Some(save)) => {
+            let name = save.value_of("name").context("Missing query name.")?;
+            let expr = save
+                .values_of("expr")
+                .context("Missing query to save.")?
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            if notes.queries_mut().save(name, &expr)? {
+                println!("Query `{}` updated.", name.trim_start_matches('@'));
+            } else {
+                println!("Query `{}` saved.", name.trim_start_matches('@'));
+            }
+        }
+        ("run", Some(run)) => {
+            let name = run.value_of("name").context("Missing query name.")?;
+            let query = Query::parse(&notes.queries().get(name)?.expr)?;
+
+            print_query(notes, &query, run, settings)?;
+        }
+        ("delete", Some(delete)) => {
+            let name = delete.value_of("name").context("Missing query name.")?;
+
+            let query = notes.queries_mut().delete(name)?;
+
+            println!("Query `{}: {}` deleted.", query.name, query.expr);
+        }
+        _ => {
+            if let Some(counts) = notes.get_saved_query_counts() {
+                let rows = counts
+                    .iter()
+                    .map(|count| vec![count.name().clone(), count.query().clone(), count.notes()])
+                    .collect();
+
+                print_table(
+                    &table(
+                        &[
+                            ("Name", Style::new().bold()),
+                            ("Query", Style::new()),
+                            ("Notes", Style::new()),
+                        ],
+                        rows,
+                        &settings.theme,
+                    ),
+                    settings,
+                )?;
+            } else {
+                println!("There are no saved queries.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-applies the auto-tagging rules to all notes, printing the tags that are
+/// added to each note.
+fn run_retag<'a>(
+    notes: &mut Notes,
+    args: &ArgMatches<'a>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let dry_run = args.is_present("dry_run");
+
+    let changes = if dry_run {
+        notes.get_retag_changes()?
+    } else {
+        notes.retag()?
+    };
+
+    if let Some(changes) = changes {
+        let rows = changes
+            .iter()
+            .map(|change| {
+                vec![
+                    change.id().to_string(),
+                    change.content().clone(),
+                    change.added().cell_text(notes.registry(), &settings.theme),
+                ]
+            })
+            .collect();
+
+        print_table(
+            &table(
+                &[
+                    ("Id", Style::new().bold()),
+                    ("Content", Style::new()),
+                    ("Added", Style::new()),
+                ],
+                rows,
+                &settings.theme,
+            ),
+            settings,
+        )?;
+
+        if dry_run {
+            println!("Dry run; no notes were changed.");
+        } else {
+            println!("{} note(s) retagged.", changes.len());
+        }
+    } else {
+        println!("No notes need retagging.");
+    }
+
+    Ok(())
+}
+
 /// Creates a new note from a file.
 fn new_note_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Note> {
     if file_is_dir(&path)? {
@@ -297,6 +1448,20 @@ fn new_note_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Note> {
     }
 }
 
+/// Returns the editor given by `--editor`, or by the config file unless
+/// `VISUAL` or `EDITOR` is set. None leaves the choice to the environment.
+fn editor(args: &ArgMatches<'_>, settings: &Settings) -> Option<String> {
+    let from_env = ["VISUAL", "EDITOR"]
+        .iter()
+        .any(|var| env::var_os(var).is_some_and(|v| !v.is_empty()));
+
+    match args.value_of("editor") {
+        Some(editor) => Some(editor.to_string()),
+        None if from_env => None,
+        None => settings.config.editor.clone(),
+    }
+}
+
 /// Opens the user's defualt editor to create a note.
 fn new_note_from_editor(editor: Option<&str>) -> anyhow::Result<Note> {
     // No edit message because it's annoying from a user viewpoint.
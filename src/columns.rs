@@ -0,0 +1,279 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Error;
+use console::Style;
+use heck::TitleCase;
+use prettytable::{Cell, Row, Table};
+
+use crate::display::{fit_widths, new_table, text_width, truncate_lines, CellText};
+use crate::errors::NotesError;
+use crate::notes::{AsNoteWithId, NoteWithId, RelatedNote, SearchMatch};
+use crate::registry::TagRegistry;
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+/// A column of the notes table, chosen at runtime with `--columns`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Id,
+    Created,
+    Modified,
+    Tags,
+    Title,
+    Content,
+    Desc,
+    Contexts,
+    Projects,
+    Length,
+    Similarity,
+}
+
+impl Column {
+    /// Every column, in the order they are listed in errors.
+    pub const ALL: [Column; 11] = [
+        Column::Id,
+        Column::Created,
+        Column::Modified,
+        Column::Tags,
+        Column::Title,
+        Column::Content,
+        Column::Desc,
+        Column::Contexts,
+        Column::Projects,
+        Column::Length,
+        Column::Similarity,
+    ];
+
+    /// The columns printed when none are given.
+    pub const DEFAULT: [Column; 4] = [Column::Id, Column::Created, Column::Tags, Column::Content];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Created => "created",
+            Column::Modified => "modified",
+            Column::Tags => "tags",
+            Column::Title => "title",
+            Column::Content => "content",
+            Column::Desc => "desc",
+            Column::Contexts => "contexts",
+            Column::Projects => "projects",
+            Column::Length => "length",
+            Column::Similarity => "similarity",
+        }
+    }
+
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Column::Id => theme.id.clone(),
+            Column::Created | Column::Modified => theme.time.clone(),
+            _ => Style::new(),
+        }
+    }
+
+    /// Checks whether the column holds free text that can be truncated to fit
+    /// the table in the terminal.
+    fn is_flexible(self) -> bool {
+        matches!(
+            self,
+            Column::Tags
+                | Column::Title
+                | Column::Content
+                | Column::Desc
+                | Column::Contexts
+                | Column::Projects
+        )
+    }
+
+    /// The text of this column for a note. Columns that only some listings
+    /// have, such as similarity, are empty.
+    pub fn text(
+        self,
+        note: &NoteWithId<'_>,
+        registry: &TagRegistry,
+        settings: &Settings,
+    ) -> String {
+        match self {
+            Column::Id => note.id().to_string(),
+            Column::Created => settings.display_time(note.created()),
+            Column::Modified => settings.display_time(note.modified()),
+            Column::Tags => note.tags().cell_text(registry, &settings.theme),
+            Column::Title => note.title(),
+            Column::Content => note.content().clone(),
+            Column::Desc => note.desc().clone(),
+            Column::Contexts => note.contexts().join(","),
+            Column::Projects => note.projects().join(","),
+            Column::Length => note.content().chars().count().to_string(),
+            Column::Similarity => String::new(),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Column::ALL
+            .iter()
+            .copied()
+            .find(|column| column.name() == s.trim())
+            .ok_or_else(|| Error::new(NotesError::InvalidColumn(s.to_string())))
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An item that can be printed as a row of the notes table. Listings that
+/// know more about a note, such as search matches, override its cells.
+pub trait NoteRow<'a>: AsNoteWithId<'a> {
+    fn cell(&self, column: Column, registry: &TagRegistry, settings: &Settings) -> String {
+        column.text(&self.as_note_with_id(), registry, settings)
+    }
+}
+
+impl<'a> NoteRow<'a> for NoteWithId<'a> {}
+
+impl<'a> NoteRow<'a> for SearchMatch<'a> {
+    fn cell(&self, column: Column, registry: &TagRegistry, settings: &Settings) -> String {
+        let theme = &settings.theme;
+        match column {
+            Column::Tags => self.tags().cell_text(registry, theme),
+            Column::Title => self.title().cell_text(registry, theme),
+            Column::Content => self.content().cell_text(registry, theme),
+            Column::Desc => self.desc().cell_text(registry, theme),
+            _ => column.text(&self.note, registry, settings),
+        }
+    }
+}
+
+impl<'a> NoteRow<'a> for RelatedNote<'a> {
+    fn cell(&self, column: Column, registry: &TagRegistry, settings: &Settings) -> String {
+        match column {
+            Column::Similarity => self.similarity(),
+            _ => column.text(&self.note, registry, settings),
+        }
+    }
+}
+
+/// Builds a table of notes with the given columns. Given a width, free text
+/// columns are truncated so that the table fits in it.
+pub fn notes_table<'a, T: NoteRow<'a>>(
+    notes: &[T],
+    columns: &[Column],
+    registry: &TagRegistry,
+    width: Option<usize>,
+    settings: &Settings,
+) -> Table {
+    let header: Vec<String> = columns
+        .iter()
+        .map(|column| column.name().to_title_case())
+        .collect();
+    let mut rows: Vec<Vec<String>> = notes
+        .iter()
+        .map(|note| {
+            columns
+                .iter()
+                .map(|column| note.cell(*column, registry, settings))
+                .collect()
+        })
+        .collect();
+
+    if let Some(width) = width {
+        let natural: Vec<usize> = (0..columns.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| text_width(&row[i]))
+                    .chain(std::iter::once(text_width(&header[i])))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let flexible: Vec<bool> = columns.iter().map(|column| column.is_flexible()).collect();
+        let widths = fit_widths(&natural, &flexible, width);
+
+        for row in rows.iter_mut() {
+            for (cell, width) in row.iter_mut().zip(&widths) {
+                *cell = truncate_lines(cell, *width);
+            }
+        }
+    }
+
+    let mut table = new_table();
+
+    table.add_row(Row::new(
+        header
+            .iter()
+            .map(|name| Cell::new(&Theme::paint(&settings.theme.header, name)))
+            .collect(),
+    ));
+
+    for row in rows {
+        table.add_row(Row::new(
+            row.iter()
+                .zip(columns)
+                .map(|(text, column)| {
+                    Cell::new(&Theme::paint(&column.style(&settings.theme), text))
+                })
+                .collect(),
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::tags::Tags;
+    use prettytable::row;
+
+    #[test]
+    fn build_notes_table() -> anyhow::Result<()> {
+        console::set_colors_enabled(true);
+        let header = |name: &str| console::style(name).underlined().to_string();
+        let bold = |text: &str| console::style(text).bold().to_string();
+
+        let note = &NoteBuilder::new()
+            .with_content("Deploy\nthe server")
+            .with_tags(Tags::from(vec!["ops"]))
+            .build();
+        let note = NoteWithId(2, note);
+        let columns = "title,id,length"
+            .split(',')
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<Column>>>()?;
+
+        let mut table = new_table();
+        table.add_row(row!(header("Title"), header("Id"), header("Length")));
+        table.add_row(row!("Deploy", bold("2"), "17"));
+
+        let registry = TagRegistry::default();
+        let settings = Settings::default();
+        assert_eq!(
+            notes_table(&[note], &columns, &registry, None, &settings),
+            table
+        );
+        assert_eq!(
+            notes_table(&[note], &columns, &registry, Some(80), &settings),
+            table
+        );
+
+        let content = vec![Column::Id, Column::Content];
+        let mut table = new_table();
+        table.add_row(row!(header("Id"), header("Content")));
+        table.add_row(row!(bold("2"), "Deploy\nthe ser…"));
+        assert_eq!(
+            notes_table(&[note], &content, &registry, Some(10), &settings),
+            table
+        );
+        assert!("tilte".parse::<Column>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::columns::Column;
+use crate::dates::TimeFormat;
+use crate::errors::NotesError;
+use crate::notes::SortKey;
+use crate::theme::{ColorChoice, Theme};
+use crate::util::{config_dir, write_atomic};
+
+/// Defaults read from `config.toml` in the config directory. Arguments and
+/// environment variables take precedence over them.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub path: Option<String>,
+    pub editor: Option<String>,
+    pub columns: Option<Vec<String>>,
+    pub time_format: Option<String>,
+    pub color: Option<String>,
+    pub theme: Option<String>,
+    pub pager: Option<String>,
+    pub sort: Option<String>,
+    /// Whether to ask before deleting a note.
+    pub confirm: Option<bool>,
+    /// Tags given to new notes that are created without `--tags`.
+    pub tags: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Every setting, in the order they are listed.
+    pub const KEYS: [&'static str; 10] = [
+        "path",
+        "editor",
+        "columns",
+        "time-format",
+        "color",
+        "theme",
+        "pager",
+        "sort",
+        "confirm",
+        "tags",
+    ];
+
+    /// Returns the path of the config file, in the config directory.
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Reads the config file, or returns an empty config if there is none.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        if !path.as_ref().exists() {
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(&path)?;
+
+        toml::from_str(&contents).map_err(|e| {
+            Error::new(NotesError::ConfigDeserialization(
+                path.as_ref().display().to_string(),
+                e.to_string(),
+            ))
+        })
+    }
+
+    /// Writes the config file, creating the config directory if needed.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        write_atomic(path, toml::to_string(self)?.as_bytes())
+    }
+
+    /// Returns the notes path, with a leading `~` standing for the home
+    /// directory.
+    pub fn notes_path(&self) -> Option<PathBuf> {
+        let path = self.path.as_ref()?;
+
+        match (path.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(PathBuf::from(path)),
+        }
+    }
+
+    /// Returns a setting as it is given to `config set`, with lists joined
+    /// by commas, or None if it is not set.
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let value = match key {
+            "path" => self.path.clone(),
+            "editor" => self.editor.clone(),
+            "columns" => self.columns.as_ref().map(|columns| columns.join(",")),
+            "time-format" => self.time_format.clone(),
+            "color" => self.color.clone(),
+            "theme" => self.theme.clone(),
+            "pager" => self.pager.clone(),
+            "sort" => self.sort.clone(),
+            "confirm" => self.confirm.map(|confirm| confirm.to_string()),
+            "tags" => self.tags.as_ref().map(|tags| tags.join(",")),
+            _ => return Err(Error::new(NotesError::InvalidConfigKey(key.to_string()))),
+        };
+
+        Ok(value)
+    }
+
+    /// Changes a setting, checking that the value is one that the matching
+    /// argument accepts. An empty value removes the setting.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        let value = Some(value.trim()).filter(|value| !value.is_empty());
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        match key {
+            "path" => self.path = value.map(String::from),
+            "editor" => self.editor = value.map(String::from),
+            "columns" => {
+                if let Some(columns) = value {
+                    for column in list(columns) {
+                        column.parse::<Column>()?;
+                    }
+                }
+                self.columns = value.map(list);
+            }
+            "time-format" => {
+                value.map(str::parse::<TimeFormat>).transpose()?;
+                self.time_format = value.map(String::from);
+            }
+            "color" => {
+                value.map(str::parse::<ColorChoice>).transpose()?;
+                self.color = value.map(String::from);
+            }
+            "theme" => {
+                value.map(str::parse::<Theme>).transpose()?;
+                self.theme = value.map(String::from);
+            }
+            "pager" => self.pager = value.map(String::from),
+            "sort" => {
+                value.map(str::parse::<SortKey>).transpose()?;
+                self.sort = value.map(String::from);
+            }
+            "confirm" => {
+                self.confirm = value
+                    .map(|value| {
+                        value.parse::<bool>().map_err(|_| {
+                            Error::new(NotesError::InvalidConfigValue(
+                                key.to_string(),
+                                value.to_string(),
+                            ))
+                        })
+                    })
+                    .transpose()?;
+            }
+            "tags" => self.tags = value.map(list),
+            _ => return Err(Error::new(NotesError::InvalidConfigKey(key.to_string()))),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the settings that are set, in the order of `KEYS`.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        Config::KEYS
+            .iter()
+            .filter_map(|key| match self.get(key) {
+                Ok(Some(value)) => Some((*key, value)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn read_and_write_config() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("notes").join("config.toml");
+        assert_eq!(Config::from_file(&path)?, Config::default());
+
+        let mut config = Config::default();
+        config.set("columns", "id, title")?;
+        config.set("sort", "modified")?;
+        config.set("confirm", "false")?;
+        config.set("tags", "inbox")?;
+        config.to_file(&path)?;
+
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "columns = [\"id\", \"title\"]\nsort = \"modified\"\nconfirm = false\ntags = [\"inbox\"]\n"
+        );
+        assert_eq!(Config::from_file(&path)?, config);
+        assert_eq!(config.get("columns")?, Some("id,title".to_string()));
+        assert_eq!(config.get("editor")?, None);
+
+        config.set("sort", "")?;
+        assert_eq!(config.sort, None);
+
+        assert!(config.set("columns", "id,tilte").is_err());
+        assert!(config.set("confirm", "maybe").is_err());
+        assert!(config.set("colour", "never").is_err());
+
+        fs::write(&path, "colour = \"never\"\n")?;
+        assert!(Config::from_file(&path).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Weekday};
+
+use crate::errors::NotesError;
+use crate::notes::Note;
+
+/// The timestamp of a note that a date filter applies to.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimeField {
+    #[default]
+    Created,
+    Modified,
+}
+
+/// An inclusive range of days that a note's created or modified time must
+/// fall in. Either end may be left open.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeFilter {
+    pub field: TimeField,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl TimeFilter {
+    /// Builds a filter from the `--since`, `--until` and `--on` arguments.
+    /// `--on` sets both ends of the range.
+    pub fn parse(
+        field: TimeField,
+        since: Option<&str>,
+        until: Option<&str>,
+        on: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let today = Local::today().naive_local();
+        let mut filter = TimeFilter {
+            field,
+            since: since.map(|s| parse_date(s, today)).transpose()?,
+            until: until.map(|s| parse_date(s, today)).transpose()?,
+        };
+
+        if let Some(on) = on {
+            let day = parse_date(on, today)?;
+            filter.since = Some(day);
+            filter.until = Some(day);
+        }
+
+        Ok(filter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Checks whether the note's time falls in the range. Notes whose time
+    /// cannot be read only match an empty filter.
+    pub fn matches(&self, note: &Note) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let time = match self.field {
+            TimeField::Created => &note.created,
+            TimeField::Modified => &note.modified,
+        };
+
+        match day(time) {
+            Some(day) => {
+                self.since.is_none_or(|since| day >= since)
+                    && self.until.is_none_or(|until| day <= until)
+            }
+            None => false,
+        }
+    }
+}
+
+/// Returns the day of a note timestamp, if it can be read.
+pub fn day(time: &str) -> Option<NaiveDate> {
+    time.get(..10).and_then(|d| d.parse::<NaiveDate>().ok())
+}
+
+/// Parses a day given as an ISO date (`2020-06-01`), a duration before today
+/// (`7d`, `2w`), or a word (`today`, `yesterday`, `last-monday`).
+pub fn parse_date(s: &str, today: NaiveDate) -> anyhow::Result<NaiveDate> {
+    let invalid = || Error::new(NotesError::InvalidDate(s.to_string()));
+    let lower = s.to_lowercase();
+
+    if let Ok(date) = lower.parse::<NaiveDate>() {
+        return Ok(date);
+    }
+
+    match lower.as_str() {
+        "today" => return Ok(today),
+        "yesterday" => return Ok(today - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Some(day) = lower.strip_prefix("last-") {
+        let weekday = day.parse::<Weekday>().map_err(|_| invalid())?;
+        let mut days_ago =
+            (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        if days_ago == 0 {
+            days_ago = 7;
+        }
+
+        return Ok(today - Duration::days(days_ago.into()));
+    }
+
+    let last = lower.char_indices().last().map_or(0, |(i, _)| i);
+    let (count, unit) = lower.split_at(last);
+    let count = count
+        .parse::<i64>()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(invalid)?;
+
+    let days = match unit {
+        "d" => Some(count),
+        "w" => count.checked_mul(7),
+        _ => None,
+    };
+
+    // Durations panic past this many days, long before dates run out.
+    days.filter(|days| *days <= i64::MAX / 1000 / 86400)
+        .and_then(|days| today.checked_sub_signed(Duration::days(days)))
+        .ok_or_else(invalid)
+}
+
+/// The format note timestamps are stored in, in local time.
+const STORED_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// How timestamps are displayed. They are always stored in the same format,
+/// so this only changes how tables print them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TimeFormat {
+    /// As stored, such as `2020-06-03 14:30:00`.
+    #[default]
+    Local,
+    /// Relative to now, such as `3h ago` or `last Tuesday`.
+    Relative,
+    /// ISO 8601 with the timezone offset, such as `2020-06-03T14:30:00+02:00`.
+    Iso,
+    /// A strftime pattern, such as `%d %b %Y`.
+    Pattern(String),
+}
+
+impl TimeFormat {
+    /// Renders a stored timestamp. Timestamps that cannot be read are
+    /// returned as they are.
+    pub fn render(&self, stored: &str, now: DateTime<Local>) -> String {
+        let time = match NaiveDateTime::parse_from_str(stored, STORED_FORMAT)
+            .ok()
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+        {
+            Some(time) => time,
+            None => return stored.to_string(),
+        };
+
+        match self {
+            TimeFormat::Local => stored.to_string(),
+            TimeFormat::Relative => relative_time(time, now),
+            TimeFormat::Iso => time.to_rfc3339(),
+            TimeFormat::Pattern(pattern) => time.format(pattern).to_string(),
+        }
+    }
+}
+
+impl FromStr for TimeFormat {
+    type Err = Error;
+
+    /// Parses `local`, `relative`, `iso`, or a strftime pattern.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "local" => Ok(TimeFormat::Local),
+            "relative" => Ok(TimeFormat::Relative),
+            "iso" => Ok(TimeFormat::Iso),
+            _ if s.contains('%') && !StrftimeItems::new(s).any(|i| i == Item::Error) => {
+                Ok(TimeFormat::Pattern(s.to_string()))
+            }
+            _ => Err(Error::new(NotesError::InvalidTimeFormat(s.to_string()))),
+        }
+    }
+}
+
+/// Describes how long ago a time was: minutes and hours within a day, then
+/// `yesterday` and `last <weekday>` within a week, then weeks, months and
+/// years.
+fn relative_time(time: DateTime<Local>, now: DateTime<Local>) -> String {
+    let elapsed = now.signed_duration_since(time);
+
+    if elapsed < Duration::minutes(1) {
+        return "just now".to_string();
+    } else if elapsed < Duration::hours(1) {
+        return format!("{}m ago", elapsed.num_minutes());
+    } else if elapsed < Duration::days(1) {
+        return format!("{}h ago", elapsed.num_hours());
+    }
+
+    let days = (now.date().naive_local() - time.date().naive_local()).num_days();
+
+    match days {
+        1 => "yesterday".to_string(),
+        2..=6 => format!("last {}", weekday_name(time.weekday())),
+        7..=29 => format!("{}w ago", days / 7),
+        30..=364 => format!("{}mo ago", days / 30),
+        _ => format!("{}y ago", days / 365),
+    }
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dates() -> anyhow::Result<()> {
+        // A Wednesday.
+        let today = NaiveDate::from_ymd(2020, 6, 3);
+
+        assert_eq!(
+            parse_date("2020-01-31", today)?,
+            NaiveDate::from_ymd(2020, 1, 31)
+        );
+        assert_eq!(parse_date("today", today)?, today);
+        assert_eq!(
+            parse_date("Yesterday", today)?,
+            NaiveDate::from_ymd(2020, 6, 2)
+        );
+        assert_eq!(parse_date("7d", today)?, NaiveDate::from_ymd(2020, 5, 27));
+        assert_eq!(parse_date("2w", today)?, NaiveDate::from_ymd(2020, 5, 20));
+        assert_eq!(
+            parse_date("last-monday", today)?,
+            NaiveDate::from_ymd(2020, 6, 1)
+        );
+        assert_eq!(
+            parse_date("last-wed", today)?,
+            NaiveDate::from_ymd(2020, 5, 27)
+        );
+        assert_eq!(
+            parse_date("last-friday", today)?,
+            NaiveDate::from_ymd(2020, 5, 29)
+        );
+
+        for invalid in &[
+            "",
+            "d",
+            "7y",
+            "-7d",
+            "0w",
+            "last-week",
+            "2020-13-01",
+            "99999999999d",
+            "9999999999999999w",
+            "99999999999999999999d",
+        ] {
+            assert!(parse_date(invalid, today).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_times() -> anyhow::Result<()> {
+        // A Wednesday afternoon.
+        let now = Local.ymd(2020, 6, 3).and_hms(15, 0, 0);
+        let render = |format: &str, stored: &str| -> anyhow::Result<String> {
+            Ok(format.parse::<TimeFormat>()?.render(stored, now))
+        };
+
+        assert_eq!(
+            render("local", "2020-06-03 14:30:00")?,
+            "2020-06-03 14:30:00"
+        );
+        assert_eq!(render("%d %b %Y", "2020-06-03 14:30:00")?, "03 Jun 2020");
+        assert!(render("iso", "2020-06-03 14:30:00")?.starts_with("2020-06-03T14:30:00"));
+        assert_eq!(render("iso", "not a time")?, "not a time");
+
+        for (stored, relative) in &[
+            ("2020-06-03 14:59:30", "just now"),
+            ("2020-06-03 14:30:00", "30m ago"),
+            ("2020-06-03 12:00:00", "3h ago"),
+            ("2020-06-02 09:00:00", "yesterday"),
+            ("2020-05-30 09:00:00", "last Saturday"),
+            ("2020-05-20 09:00:00", "2w ago"),
+            ("2020-02-01 09:00:00", "4mo ago"),
+            ("2018-01-01 09:00:00", "2y ago"),
+        ] {
+            assert_eq!(render("relative", stored)?, *relative);
+        }
+
+        assert!("%Q".parse::<TimeFormat>().is_err());
+        assert!("short".parse::<TimeFormat>().is_err());
+
+        Ok(())
+    }
+}
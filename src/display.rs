@@ -1,38 +1,241 @@
-use prettytable::{format, Table};
+use std::fmt;
+use std::ops::Range;
 
-#[macro_export]
-macro_rules! build_row {
-    ($note:expr, [$(($name:ident, $style:expr)),+]) => {{
-        use prettytable::{Row, Cell};
+use console::Style;
+use prettytable::{format, Cell, Row, Table};
 
-        Row::new(vec![
-            $(Cell::new(&$note.$name().clone().to_string()).style_spec($style)),+
-        ])
-    }};
+use crate::pager;
+use crate::registry::TagRegistry;
+use crate::settings::Settings;
+use crate::tags::*;
+use crate::theme::Theme;
+
+/// The text of a table cell. Tags are rendered in their registered colors.
+pub trait CellText {
+    fn cell_text(&self, registry: &TagRegistry, theme: &Theme) -> String;
+}
+
+impl CellText for String {
+    fn cell_text(&self, _registry: &TagRegistry, _theme: &Theme) -> String {
+        self.clone()
+    }
+}
+
+impl CellText for usize {
+    fn cell_text(&self, _registry: &TagRegistry, _theme: &Theme) -> String {
+        self.to_string()
+    }
+}
+
+impl CellText for Tag {
+    fn cell_text(&self, registry: &TagRegistry, theme: &Theme) -> String {
+        registry.paint(self, theme)
+    }
+}
+
+impl CellText for Tags {
+    fn cell_text(&self, registry: &TagRegistry, theme: &Theme) -> String {
+        self.tags()
+            .iter()
+            .map(|tag| registry.paint(tag, theme))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+/// A value with byte ranges that are highlighted when it is displayed in a
+/// table, such as the matches of a search.
+#[derive(Clone)]
+pub struct Highlighted<T> {
+    value: T,
+    spans: Vec<Range<usize>>,
+}
+
+impl<T> Highlighted<T> {
+    pub fn new(value: T, spans: Vec<Range<usize>>) -> Self {
+        Highlighted { value, spans }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Highlighted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl CellText for Highlighted<String> {
+    fn cell_text(&self, _registry: &TagRegistry, theme: &Theme) -> String {
+        highlight(&self.value, &self.spans, theme)
+    }
+}
+
+impl CellText for Highlighted<Tags> {
+    fn cell_text(&self, registry: &TagRegistry, theme: &Theme) -> String {
+        if self.spans.is_empty() {
+            self.value.cell_text(registry, theme)
+        } else {
+            highlight(&self.value.to_string(), &self.spans, theme)
+        }
+    }
+}
+
+/// Highlights the given byte ranges of some text in the theme's highlight
+/// style.
+pub fn highlight(text: &str, spans: &[Range<usize>], theme: &Theme) -> String {
+    let mut spans = spans.to_vec();
+    spans.sort_by_key(|span| span.start);
+
+    let mut highlighted = String::new();
+    let mut end = 0;
+
+    for span in spans {
+        if span.start < end {
+            continue;
+        }
+
+        highlighted.push_str(&text[end..span.start]);
+
+        highlighted.push_str(&Theme::paint(&theme.highlight, &text[span.clone()]));
+
+        end = span.end;
+    }
+
+    highlighted.push_str(&text[end..]);
+
+    highlighted
+}
+
+/// The narrowest a column is shrunk to when fitting a table to the terminal.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// Returns the width of the terminal that stdout is attached to, or the
+/// `COLUMNS` environment variable if it is not a terminal. Returns `None` if
+/// neither is known, such as when output is piped to another program.
+pub fn terminal_width() -> Option<usize> {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, width)| width as usize)
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|width| *width > 0)
+}
+
+/// Returns the display width of the widest line of some text. ANSI styles
+/// take up no width, and wide characters such as CJK and emoji take up two.
+pub fn text_width(text: &str) -> usize {
+    text.lines()
+        .map(console::measure_text_width)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Shrinks the widths of the flexible columns of a table, separated by single
+/// spaces, so that it fits in `width`. Narrow columns keep their width and
+/// wide ones share what is left evenly, but none shrink below a minimum.
+pub fn fit_widths(natural: &[usize], flexible: &[bool], width: usize) -> Vec<usize> {
+    let separators = natural.len().saturating_sub(1);
+
+    if natural.iter().sum::<usize>() + separators <= width {
+        return natural.to_vec();
+    }
+
+    let fixed: usize = natural
+        .iter()
+        .zip(flexible)
+        .filter(|(_, flexible)| !**flexible)
+        .map(|(width, _)| width)
+        .sum();
+    let mut remaining = width.saturating_sub(fixed + separators);
+
+    let mut order: Vec<usize> = (0..natural.len()).filter(|i| flexible[*i]).collect();
+    order.sort_by_key(|i| natural[*i]);
+
+    let mut widths = natural.to_vec();
+    for (n, i) in order.iter().enumerate() {
+        let share = remaining / (order.len() - n);
+        widths[*i] = natural[*i].min(share.max(MIN_COLUMN_WIDTH));
+        remaining = remaining.saturating_sub(widths[*i]);
+    }
+
+    widths
+}
+
+/// Truncates each line of some text to a display width, ending cut lines
+/// with an ellipsis. ANSI styles are kept.
+pub fn truncate_lines(text: &str, width: usize) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+
+    text.split('\n')
+        .map(|line| console::truncate_str(line, width.max(1), "…").into_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
-#[macro_export]
-macro_rules! build_table {
-    ($notes:expr, [$(($name:ident, $style:expr)),+]) => {{
-        use crate::build_row;
-        use crate::display::new_table;
-        use prettytable::{Cell, Row};
-        use heck::TitleCase;
+/// Prints a table to stdout, through the pager if it is taller than the
+/// terminal.
+pub fn print_table(table: &Table, settings: &Settings) -> anyhow::Result<()> {
+    pager::page(table.to_string().trim_end_matches('\n'), settings)
+}
 
-        let mut table = new_table();
+/// Formats a number of bytes for people, such as `812 B` or `4.2 KiB`.
+pub fn file_size(bytes: u64) -> String {
+    let units = ["KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = "B";
 
-        table.add_row(Row::new(vec![
-            $(Cell::new(&stringify!($name).to_title_case()).style_spec("u")),+
-        ]));
+    for next in units.iter() {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
 
-        $notes.iter().for_each(|note| {
-            table.add_row(build_row!(note,
-                [$(($name, $style)),+]
-            ));
-        });
+    match unit {
+        "B" => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, unit),
+    }
+}
 
-        table
-    }};
+/// Joins the sections of a grouped listing, each under a heading with the
+/// name of its group and its number of notes.
+pub fn sections<I>(sections: I, theme: &Theme) -> String
+where
+    I: IntoIterator<Item = (String, usize, String)>,
+{
+    sections
+        .into_iter()
+        .map(|(name, count, body)| {
+            let heading = format!("{} ({})", name, count);
+            format!("{}\n{}", Theme::paint(&theme.header, &heading), body)
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Builds a table with the given headings and rows, painting the cells of
+/// each column in its style. Notes tables are built by `notes_table`.
+pub fn table(columns: &[(&str, Style)], rows: Vec<Vec<String>>, theme: &Theme) -> Table {
+    let mut table = new_table();
+
+    table.add_row(Row::new(
+        columns
+            .iter()
+            .map(|(name, _)| Cell::new(&Theme::paint(&theme.header, name)))
+            .collect(),
+    ));
+
+    for row in rows {
+        table.add_row(Row::new(
+            row.iter()
+                .zip(columns)
+                .map(|(text, (_, style))| Cell::new(&Theme::paint(style, text)))
+                .collect(),
+        ));
+    }
+
+    table
 }
 
 pub fn new_table() -> Table {
@@ -56,44 +259,113 @@ pub fn new_table() -> Table {
 mod tests {
     use super::*;
     use crate::notes::{NoteBuilder, NoteWithId};
-    use prettytable::{cell, row};
+    use crate::registry::TagColor;
+    use console::style;
+    use prettytable::row;
 
     #[test]
-    fn build_row() {
-        let note = &NoteBuilder::new()
-            .with_content("Test.")
-            .with_desc("Test.")
-            .build();
-        let note = NoteWithId(0, note);
-        let row = row!(note.content().clone(), note.desc().clone());
-        let our_row = build_row!(note, [(content, ""), (desc, "")]);
+    fn highlight_spans() {
+        console::set_colors_enabled(true);
 
-        assert_eq!(row, our_row);
+        assert_eq!(
+            highlight(
+                "deploy the deploy\nscript",
+                &[11..24, 0..6, 2..4],
+                &Theme::default()
+            ),
+            format!(
+                "{} the {}\n{}",
+                style("deploy").yellow().bold(),
+                style("deploy").yellow().bold(),
+                style("script").yellow().bold()
+            )
+        );
+    }
+
+    #[test]
+    fn fit_and_truncate() {
+        assert_eq!(
+            fit_widths(&[2, 10, 30], &[false, true, true], 80),
+            [2, 10, 30]
+        );
+        assert_eq!(
+            fit_widths(&[2, 10, 30], &[false, true, true], 30),
+            [2, 10, 16]
+        );
+        assert_eq!(
+            fit_widths(&[2, 40, 30], &[false, true, true], 30),
+            [2, 13, 13]
+        );
+        assert_eq!(
+            fit_widths(&[2, 40, 30], &[false, true, true], 10),
+            [2, 8, 8]
+        );
+
+        assert_eq!(text_width("日本語のメモ\nabc"), 12);
+        assert_eq!(truncate_lines("日本語のメモ\nabcdefg", 5), "日本…\nabcd…");
+        assert_eq!(truncate_lines("short", 5), "short");
+
+        console::set_colors_enabled(true);
+        let styled = style("deploy").yellow().to_string();
+        assert_eq!(text_width(&styled), 6);
+        assert_eq!(
+            console::strip_ansi_codes(&truncate_lines(&styled, 4)),
+            "dep…"
+        );
     }
 
     #[test]
     fn build_table() {
+        console::set_colors_enabled(true);
+
+        let mut registry = TagRegistry::default();
+        registry.get_or_insert(Tag::from("red")).color = Some(TagColor::Red);
+
         let note1 = &NoteBuilder::new()
             .with_content("Test.")
-            .with_desc("Test.")
+            .with_tags(Tags::from(vec!["red", "plain"]))
             .build();
         let note1 = NoteWithId(0, note1);
-        let note2 = &NoteBuilder::new()
-            .with_content("Second Test.")
-            .with_desc("A description.")
-            .build();
+        let note2 = &NoteBuilder::new().with_content("Second Test.").build();
         let note2 = NoteWithId(1, note2);
 
-        let mut table = new_table();
-        table.add_row(row!(u -> "Content", u -> "Desc"));
-        table.add_row(row!(note1.content().clone(), note1.desc().clone()));
-        table.add_row(row!(note2.content().clone(), note2.desc().clone()));
-
-        let our_table = build_table!(vec![note1, note2], [(content, ""), (desc, "")]);
+        let mut expected = new_table();
+        expected.add_row(row!(
+            style("Id").underlined().to_string(),
+            style("Tags").underlined().to_string(),
+            style("Content").underlined().to_string()
+        ));
+        expected.add_row(row!(
+            style("0").bold().to_string(),
+            format!("{},plain", style("red").red()),
+            note1.content().clone()
+        ));
+        expected.add_row(row!(
+            style("1").bold().to_string(),
+            "",
+            note2.content().clone()
+        ));
 
-        dbg!(&table);
-        dbg!(&our_table);
+        let rows = [note1, note2]
+            .iter()
+            .map(|note| {
+                vec![
+                    note.id().to_string(),
+                    note.tags().cell_text(&registry, &Theme::default()),
+                    note.content().clone(),
+                ]
+            })
+            .collect();
+        let ours = table(
+            &[
+                ("Id", Style::new().bold()),
+                ("Tags", Style::new()),
+                ("Content", Style::new()),
+            ],
+            rows,
+            &Theme::default(),
+        );
 
-        assert_eq!(table, our_table);
+        assert_eq!(expected, ours);
     }
 }
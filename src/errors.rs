@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::output::OutputFormat;
+
 #[derive(Error, Debug)]
 pub enum NotesError {
     #[error("Note ID `{0}` is invalid.")]
@@ -10,4 +12,64 @@ pub enum NotesError {
     NewNoteFromEditor(String),
     #[error("Unable to read notes file.")]
     NoteDeserialization,
+    #[error("Notes file format version {0} is newer than this version of notes supports.")]
+    UnsupportedFormat(u16),
+    #[error("Tag `{0}` is not used by any note.")]
+    TagNotFound(String),
+    #[error("Unable to read tag registry file.")]
+    RegistryDeserialization,
+    #[error("`{0}` is not a color; expected one of black, red, green, yellow, blue, magenta, cyan, white.")]
+    InvalidColor(String),
+    #[error("Alias `{0}` already belongs to tag `{1}`.")]
+    AliasInUse(String, String),
+    #[error("Unable to read search index file.")]
+    IndexDeserialization,
+    #[error("Cannot search `{0}`; expected one of title, tags, desc, content.")]
+    InvalidSearchField(String),
+    #[error("Rule ID `{0}` is invalid.")]
+    InvalidRuleId(usize),
+    #[error("Unable to read auto-tagging rules file.")]
+    RulesDeserialization,
+    #[error("`{0}` is not a date; expected YYYY-MM-DD, a duration such as 7d or 2w, today, yesterday, or last-<weekday>.")]
+    InvalidDate(String),
+    #[error("Year `{0}` is out of range.")]
+    InvalidYear(i32),
+    #[error("Cannot sort by `{0}`; expected one of created, modified, id, tag, length.")]
+    InvalidSortKey(String),
+    #[error("Cannot group by `{0}`; expected one of tag, day, week, month.")]
+    InvalidGroupKey(String),
+    #[error("Invalid query term `{0}`: {1}.")]
+    InvalidQuery(String, String),
+    #[error("Found argument `{0}` which wasn't expected; negate a query term with a single `-`.")]
+    UnexpectedArgument(String),
+    #[error("No query is saved as `{0}`.")]
+    QueryNotFound(String),
+    #[error("`{0}` is not a valid query name; names cannot be empty or contain spaces.")]
+    InvalidQueryName(String),
+    #[error("Unable to read saved queries file.")]
+    QueriesDeserialization,
+    #[error("At least two different notes are needed to merge.")]
+    NothingToMerge,
+    #[error("Cannot output `{0}`; expected one of table, json, ndjson, csv, tsv.")]
+    InvalidOutputFormat(String),
+    #[error("`{0}` cannot be printed as {1}; use table, json or ndjson.")]
+    UnsupportedOutputFormat(String, OutputFormat),
+    #[error("Unknown column `{0}`; expected one of id, created, modified, tags, title, content, desc, contexts, projects, length, similarity.")]
+    InvalidColumn(String),
+    #[error("Cannot color `{0}`; expected one of auto, always, never.")]
+    InvalidColorChoice(String),
+    #[error("`{0}` is not a theme setting; expected key=style such as id=cyan.bold, where the keys are id, time, tag, header and highlight.")]
+    InvalidTheme(String),
+    #[error("`{0}` is not a time format; expected local, relative, iso, or a strftime pattern such as `%d %b %Y`.")]
+    InvalidTimeFormat(String),
+    #[error("Invalid template `{0}`: {1}.")]
+    InvalidTemplate(String, String),
+    #[error("No template is saved as `{0}`.")]
+    TemplateNotFound(String),
+    #[error("Unable to read config file `{0}`; fix it by hand: {1}")]
+    ConfigDeserialization(String, String),
+    #[error("Unknown config key `{0}`; expected one of path, editor, columns, time-format, color, theme, pager, sort, confirm, tags.")]
+    InvalidConfigKey(String),
+    #[error("Invalid value `{1}` for config key `{0}`.")]
+    InvalidConfigValue(String, String),
 }
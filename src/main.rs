@@ -1,18 +1,36 @@
 #![allow(dead_code)]
 
+mod activity;
 mod app;
+mod columns;
+mod config;
+mod dates;
 mod display;
 mod errors;
+mod markdown;
+mod markers;
 mod notes;
+mod output;
+mod pager;
+mod queries;
+mod registry;
+mod rules;
+mod settings;
 mod tags;
+mod template;
+mod theme;
 mod util;
 
-use crate::app::{app, run_app};
+use std::process;
 
-fn main() -> anyhow::Result<()> {
-    let app = app();
+use crate::app::{app, output_format, run_app};
+use crate::output::print_error;
 
-    run_app(app)?;
+fn main() {
+    let matches = app().get_matches();
 
-    Ok(())
+    if let Err(e) = run_app(&matches) {
+        print_error(output_format(&matches).unwrap_or_default(), &e);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,341 @@
+use std::sync::OnceLock;
+
+use console::Style;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+/// The syntect theme code blocks are highlighted with.
+const CODE_THEME: &str = "base16-ocean.dark";
+
+/// Renders Markdown for the terminal: headings, emphasis, lists, block quotes
+/// and code blocks, highlighted by language when colors are enabled. Rules
+/// are drawn `width` characters wide.
+pub fn render(markdown: &str, width: usize) -> String {
+    render_with_colors(markdown, width, console::colors_enabled())
+}
+
+fn render_with_colors(markdown: &str, width: usize, colors: bool) -> String {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut renderer = Renderer {
+        width,
+        colors,
+        ..Renderer::default()
+    };
+
+    for event in Parser::new_ext(markdown, options) {
+        renderer.event(event);
+    }
+
+    renderer.out.trim_end().to_string()
+}
+
+/// What is written at the start of each line inside a block quote or list
+/// item. List items show their bullet on the first line only.
+struct Prefix {
+    first: Option<String>,
+    rest: String,
+}
+
+#[derive(Default)]
+struct Renderer {
+    out: String,
+    width: usize,
+    colors: bool,
+    prefixes: Vec<Prefix>,
+    at_line_start: bool,
+    blank_line: bool,
+    /// The next number of each enclosing list, or `None` for bullets.
+    lists: Vec<Option<u64>>,
+    heading: Option<HeadingLevel>,
+    strong: usize,
+    emphasis: usize,
+    strikethrough: usize,
+    links: Vec<String>,
+    /// The language and text of the code block being read.
+    code: Option<(String, String)>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event<'_>) {
+        if let Some((_, code)) = self.code.as_mut() {
+            match event {
+                Event::Text(text) => code.push_str(&text),
+                Event::End(Tag::CodeBlock(_)) => self.code_block(),
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text, self.style()),
+            Event::Code(code) => self.text(&code, self.style().cyan()),
+            Event::Html(html) => self.text(&html, Style::new().dim()),
+            Event::FootnoteReference(name) => self.text(&format!("[^{}]", name), self.style()),
+            Event::SoftBreak | Event::HardBreak => self.newline(),
+            Event::Rule => {
+                self.start_block();
+                self.text(&"─".repeat(self.width), Style::new().dim());
+                self.end_block();
+            }
+            Event::TaskListMarker(done) => {
+                self.text(if done { "[x] " } else { "[ ] " }, self.style());
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph => self.start_block(),
+            Tag::Heading(level, _, _) => {
+                self.start_block();
+                self.heading = Some(level);
+                let marker = "#".repeat(level as usize);
+                self.text(&format!("{} ", marker), Style::new().dim());
+            }
+            Tag::BlockQuote => {
+                self.start_block();
+                self.prefixes.push(Prefix {
+                    first: None,
+                    rest: self.paint(&Style::new().dim(), "│ "),
+                });
+            }
+            Tag::CodeBlock(kind) => {
+                self.start_block();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.split(',').next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                if self.lists.is_empty() {
+                    self.start_block();
+                } else {
+                    self.newline();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.prefixes.push(Prefix {
+                    rest: " ".repeat(console::measure_text_width(&marker)),
+                    first: Some(marker),
+                });
+            }
+            Tag::Emphasis => self.emphasis += 1,
+            Tag::Strong => self.strong += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => self.links.push(url.to_string()),
+            Tag::FootnoteDefinition(name) => {
+                self.start_block();
+                self.text(&format!("[^{}]: ", name), Style::new().dim());
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    fn end(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::Paragraph | Tag::FootnoteDefinition(_) => self.end_block(),
+            Tag::Heading(..) => {
+                self.heading = None;
+                self.end_block();
+            }
+            Tag::BlockQuote => {
+                self.prefixes.pop();
+                self.end_block();
+            }
+            Tag::CodeBlock(_) => {}
+            Tag::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            Tag::Item => {
+                self.newline();
+                self.prefixes.pop();
+                self.blank_line = false;
+            }
+            Tag::Emphasis => self.emphasis -= 1,
+            Tag::Strong => self.strong -= 1,
+            Tag::Strikethrough => self.strikethrough -= 1,
+            Tag::Link(..) | Tag::Image(..) => {
+                if let Some(url) = self.links.pop() {
+                    self.text(&format!(" ({})", url), Style::new().dim());
+                }
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => {}
+        }
+    }
+
+    /// The style of inline text, from the elements it is inside of.
+    fn style(&self) -> Style {
+        let mut style = Style::new();
+
+        match self.heading {
+            Some(HeadingLevel::H1) => style = style.bold().underlined(),
+            Some(_) => style = style.bold(),
+            None => {}
+        }
+        if self.strong > 0 {
+            style = style.bold();
+        }
+        if self.emphasis > 0 {
+            style = style.italic();
+        }
+        if self.strikethrough > 0 {
+            style = style.dim();
+        }
+        if !self.links.is_empty() {
+            style = style.underlined();
+        }
+
+        style
+    }
+
+    /// Writes text, starting each of its lines with the prefixes of the
+    /// enclosing blocks.
+    fn text(&mut self, text: &str, style: Style) {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            if !line.is_empty() {
+                self.start_line();
+                let line = self.paint(&style, line);
+                self.out.push_str(&line);
+            }
+        }
+    }
+
+    fn paint(&self, style: &Style, text: &str) -> String {
+        style
+            .clone()
+            .force_styling(self.colors)
+            .apply_to(text)
+            .to_string()
+    }
+
+    fn start_line(&mut self) {
+        if self.at_line_start || self.out.is_empty() {
+            for prefix in self.prefixes.iter_mut() {
+                let text = prefix.first.take().unwrap_or_else(|| prefix.rest.clone());
+                self.out.push_str(&text);
+            }
+            self.at_line_start = false;
+        }
+    }
+
+    fn newline(&mut self) {
+        if !self.at_line_start && !self.out.is_empty() {
+            self.out.push('\n');
+            self.at_line_start = true;
+        }
+    }
+
+    /// Separates a block from the one before it with a blank line.
+    fn start_block(&mut self) {
+        self.newline();
+
+        if self.blank_line {
+            let prefix: String = self.prefixes.iter().map(|p| p.rest.as_str()).collect();
+            self.out.push_str(prefix.trim_end());
+            self.out.push('\n');
+            self.blank_line = false;
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.newline();
+        self.blank_line = true;
+    }
+
+    /// Writes the code block that was read, indented and highlighted by its
+    /// language when colors are enabled.
+    fn code_block(&mut self) {
+        let (lang, code) = match self.code.take() {
+            Some(code) => code,
+            None => return,
+        };
+        let code = code.trim_end_matches('\n');
+
+        let lines = if self.colors && !lang.is_empty() {
+            highlight(&lang, code).unwrap_or_else(|| code.lines().map(String::from).collect())
+        } else {
+            code.lines().map(String::from).collect()
+        };
+
+        for line in lines {
+            self.start_line();
+            self.out.push_str("    ");
+            self.out.push_str(&line);
+            self.newline();
+        }
+
+        self.end_block();
+    }
+}
+
+/// The syntaxes and themes of code blocks, loaded on the first code block.
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+/// Highlights code in the given language, one string per line, or returns
+/// `None` if the language is unknown.
+fn highlight(lang: &str, code: &str) -> Option<Vec<String>> {
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let syntax = syntaxes.find_syntax_by_token(lang)?;
+    let themes = THEMES.get_or_init(ThemeSet::load_defaults);
+    let mut highlighter = HighlightLines::new(syntax, themes.themes.get(CODE_THEME)?);
+
+    LinesWithEndings::from(code)
+        .map(|line| {
+            let ranges = highlighter.highlight_line(line, syntaxes).ok()?;
+            let escaped = as_24_bit_terminal_escaped(&ranges, false);
+            Some(format!("{}\x1b[0m", escaped.trim_end_matches('\n')))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_markdown() {
+        let markdown = "# Deploy\n\nRun the *staging* deploy with `make`.\n\n\
+                        - check **logs**\n- ping [ops](https://ops.example)\n  1. first\n  2. second\n\n\
+                        > Careful\n> with prod\n\n```sh\nmake deploy\n```\n\n---\n\nDone";
+
+        assert_eq!(
+            render_with_colors(markdown, 10, false),
+            "# Deploy\n\n\
+             Run the staging deploy with make.\n\n\
+             • check logs\n\
+             • ping ops (https://ops.example)\n  \
+               1. first\n  \
+               2. second\n\n\
+             │ Careful\n\
+             │ with prod\n\n    \
+             make deploy\n\n\
+             ──────────\n\n\
+             Done"
+        );
+
+        let highlighted = render_with_colors("```rust\nfn main() {}\n```", 10, true);
+        assert!(highlighted.starts_with("    \x1b[38;2;"));
+        assert_eq!(console::strip_ansi_codes(&highlighted), "    fn main() {}");
+    }
+}
@@ -0,0 +1,145 @@
+use crate::tags::*;
+
+/// Markers written inline in note content: `#tag`, `@context` and `+project`,
+/// in the style of todo.txt.
+#[derive(Debug, Default, PartialEq)]
+pub struct Markers {
+    pub tags: Tags,
+    pub contexts: Vec<String>,
+    pub projects: Vec<String>,
+}
+
+impl Markers {
+    /// Finds every marker in the given content.
+    pub fn parse(content: &str) -> Self {
+        let mut markers = Markers::default();
+
+        for word in content.split_whitespace() {
+            match split_marker(word) {
+                Some(('#', name, _)) => {
+                    let tag = Tag::from(name);
+                    if !markers.tags.0.contains(&tag) {
+                        markers.tags.push(tag);
+                    }
+                }
+                Some(('@', name, _)) => push_unique(&mut markers.contexts, name),
+                Some(('+', name, _)) => push_unique(&mut markers.projects, name),
+                _ => {}
+            }
+        }
+
+        markers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.0.is_empty() && self.contexts.is_empty() && self.projects.is_empty()
+    }
+}
+
+/// Removes every marker from the given content, keeping any punctuation that
+/// followed a marker. The rest of each line, indentation included, is kept as
+/// it was.
+pub fn strip_markers(content: &str) -> String {
+    content
+        .lines()
+        .map(strip_line)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Removes the markers of a line along with the space that separated them
+/// from the text before them, or from the text after them at the start of the
+/// line.
+fn strip_line(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut kept = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = line[pos..].find(|c: char| !c.is_whitespace()) {
+        let start = pos + offset;
+        let len = line[start..]
+            .find(char::is_whitespace)
+            .unwrap_or(line.len() - start);
+        pos = start + len;
+
+        let (name, trailing) = match split_marker(&line[start..pos]) {
+            Some((_, name, trailing)) => (name, trailing),
+            None => continue,
+        };
+
+        stripped.push_str(&line[kept..start]);
+        kept = start + 1 + name.len();
+
+        if !stripped.trim().is_empty() {
+            stripped.truncate(stripped.trim_end().len());
+        } else if trailing.is_empty() {
+            kept = line[pos..]
+                .find(|c: char| !c.is_whitespace())
+                .map_or(line.len(), |offset| pos + offset);
+        }
+    }
+
+    stripped.push_str(&line[kept..]);
+    stripped
+}
+
+/// Splits a word into its marker character, the marker name, and any trailing
+/// punctuation. Returns None if the word is not a marker. Names must contain a
+/// letter, so `#1` and `+1` are not markers.
+fn split_marker(word: &str) -> Option<(char, &str, &str)> {
+    let mut chars = word.chars();
+    let sigil = chars.next().filter(|c| matches!(c, '#' | '@' | '+'))?;
+
+    let rest = chars.as_str();
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '/')))
+        .unwrap_or(rest.len());
+    let name = rest[..end].trim_end_matches(['-', '_', '/']);
+    let trailing = &rest[name.len()..];
+
+    if !name.chars().any(char::is_alphabetic) || !trailing.chars().all(|c| c.is_ascii_punctuation())
+    {
+        return None;
+    }
+
+    Some((sigil, name, trailing))
+}
+
+fn push_unique(list: &mut Vec<String>, name: &str) {
+    if !list.iter().any(|n| n == name) {
+        list.push(name.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_markers() {
+        let markers = Markers::parse(
+            "fix the #deploy script for #staging. @work +infra, +1 #12 a@b.com #deploy",
+        );
+
+        assert_eq!(
+            markers,
+            Markers {
+                tags: Tags::from(vec!["deploy", "staging"]),
+                contexts: vec!["work".to_string()],
+                projects: vec!["infra".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn strip() {
+        assert_eq!(
+            strip_markers("fix the #deploy script for #staging.\n@work call +infra"),
+            "fix the script for.\ncall"
+        );
+        assert_eq!(
+            strip_markers("steps:\n    1. run   the #deploy\n  @work  - [ ] check +infra, then"),
+            "steps:\n    1. run   the\n  - [ ] check, then"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use chrono::Datelike;
+
+use crate::dates::day;
+use crate::errors::NotesError;
+
+use super::NoteWithId;
+
+/// What a listing can be grouped by. Days, weeks and months are of the
+/// created time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupKey {
+    Tag,
+    Day,
+    Week,
+    Month,
+}
+
+impl GroupKey {
+    /// The names of the groups a note is listed under: each of its tags, or
+    /// `(untagged)`, in parentheses so that it does not read as a tag named
+    /// `untagged`, or the day (`2026-10-18`), ISO week (`2026-W42`) or month
+    /// (`2026-10`) it was created in.
+    pub fn names(self, note: &NoteWithId<'_>) -> Vec<String> {
+        if self == GroupKey::Tag {
+            return if note.tags().0.is_empty() {
+                vec!["(untagged)".to_string()]
+            } else {
+                note.tags().0.iter().map(String::from).collect()
+            };
+        }
+
+        let name = match day(note.created()) {
+            Some(day) => match self {
+                GroupKey::Week => {
+                    let week = day.iso_week();
+                    format!("{}-W{:02}", week.year(), week.week())
+                }
+                GroupKey::Month => day.format("%Y-%m").to_string(),
+                _ => day.to_string(),
+            },
+            None => "unknown".to_string(),
+        };
+
+        vec![name]
+    }
+
+    /// Groups notes, keeping their order within each group. Groups are in
+    /// the order of their first note.
+    pub fn group<'a>(self, notes: Vec<NoteWithId<'a>>) -> Vec<(String, Vec<NoteWithId<'a>>)> {
+        let mut groups: Vec<(String, Vec<NoteWithId<'a>>)> = vec![];
+
+        for note in notes {
+            for name in self.names(&note) {
+                match groups.iter_mut().find(|(group, _)| *group == name) {
+                    Some((_, notes)) => notes.push(note),
+                    None => groups.push((name, vec![note])),
+                }
+            }
+        }
+
+        groups
+    }
+}
+
+impl FromStr for GroupKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "tag" => Ok(GroupKey::Tag),
+            "day" => Ok(GroupKey::Day),
+            "week" => Ok(GroupKey::Week),
+            "month" => Ok(GroupKey::Month),
+            _ => Err(Error::new(NotesError::InvalidGroupKey(s.to_string()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::tags::Tags;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn group_notes() {
+        let time = |d: u32| Local.ymd(2026, 1, d).and_hms(12, 0, 0);
+        let notes = [
+            NoteBuilder::new()
+                .with_time(time(2))
+                .with_tags(Tags::from(vec!["ops", "rust"]))
+                .build(),
+            NoteBuilder::new().with_time(time(5)).build(),
+            NoteBuilder::new()
+                .with_time(time(2))
+                .with_tags(Tags::from(vec!["rust"]))
+                .build(),
+        ];
+        let groups = |key: GroupKey| -> Vec<(String, Vec<usize>)> {
+            key.group(
+                notes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, n)| NoteWithId(i, n))
+                    .collect(),
+            )
+            .into_iter()
+            .map(|(name, notes)| (name, notes.iter().map(|n| n.id()).collect()))
+            .collect()
+        };
+
+        assert_eq!(
+            groups(GroupKey::Tag),
+            vec![
+                ("ops".to_string(), vec![0]),
+                ("rust".to_string(), vec![0, 2]),
+                ("(untagged)".to_string(), vec![1]),
+            ]
+        );
+        assert_eq!(
+            groups(GroupKey::Day),
+            vec![
+                ("2026-01-02".to_string(), vec![0, 2]),
+                ("2026-01-05".to_string(), vec![1]),
+            ]
+        );
+        assert_eq!(
+            groups(GroupKey::Week),
+            vec![
+                ("2026-W01".to_string(), vec![0, 2]),
+                ("2026-W02".to_string(), vec![1]),
+            ]
+        );
+        assert_eq!(
+            groups(GroupKey::Month),
+            vec![("2026-01".to_string(), vec![0, 1, 2])]
+        );
+        assert!("notebook".parse::<GroupKey>().is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::NotesError;
+use crate::util::{read_sidecar, write_sidecar};
+
+use super::{Note, Search, SearchField};
+
+/// An occurrence of a term in one field of a note.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Posting {
+    id: usize,
+    field: SearchField,
+    count: usize,
+}
+
+/// An inverted index from search terms to the notes that contain them, stored
+/// next to the notes file.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct SearchIndex {
+    len: usize,
+    postings: BTreeMap<String, Vec<Posting>>,
+}
+
+impl SearchIndex {
+    /// Returns the path of the index that belongs to the given notes file.
+    pub fn path_for<P: AsRef<Path>>(notes_path: P) -> PathBuf {
+        notes_path.as_ref().with_extension("index")
+    }
+
+    /// Reads the index for the given notes file. Returns None if the index is
+    /// missing, or stale because the notes file was written after it or holds
+    /// a different number of notes.
+    pub fn from_file<P: AsRef<Path>>(notes_path: P, len: usize) -> anyhow::Result<Option<Self>> {
+        let path = SearchIndex::path_for(&notes_path);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let notes_modified = fs::metadata(&notes_path)?.modified()?;
+        let index_modified = fs::metadata(&path)?.modified()?;
+        if index_modified < notes_modified {
+            return Ok(None);
+        }
+
+        let index: SearchIndex = read_sidecar(&path, NotesError::IndexDeserialization)?;
+
+        if index.len != len {
+            return Ok(None);
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Writes the index for the given notes file.
+    pub fn to_file<P: AsRef<Path>>(&self, notes_path: P) -> anyhow::Result<()> {
+        write_sidecar(self, false, SearchIndex::path_for(notes_path))
+    }
+
+    /// Builds an index of all of the given notes.
+    pub fn build(notes: &[Note]) -> Self {
+        let mut index = SearchIndex::default();
+
+        for note in notes {
+            index.push(note);
+        }
+
+        index
+    }
+
+    /// Returns the number of notes in the index.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Adds a note to the end of the index.
+    pub fn push(&mut self, note: &Note) {
+        self.add_postings(self.len, note);
+        self.len += 1;
+    }
+
+    /// Replaces the terms of the note at the given index.
+    pub fn update(&mut self, id: usize, note: &Note) {
+        self.remove_postings(id);
+        self.add_postings(id, note);
+    }
+
+    /// Removes the note at the given index, moving the notes after it down
+    /// by one, as `Notes::delete` does.
+    pub fn delete(&mut self, id: usize) {
+        self.remove_postings(id);
+
+        for postings in self.postings.values_mut() {
+            for posting in postings.iter_mut() {
+                if posting.id > id {
+                    posting.id -= 1;
+                }
+            }
+        }
+
+        self.len -= 1;
+    }
+
+    /// Scores every note that matches a search. Returns pairs of scores and
+    /// note indices, in index order.
+    pub fn search(&self, search: &Search) -> Vec<(usize, usize)> {
+        if search.terms.is_empty() {
+            return vec![];
+        }
+
+        let mut scores: Option<BTreeMap<usize, usize>> = None;
+
+        for term in search.terms.iter() {
+            let mut term_scores = BTreeMap::new();
+
+            for posting in self.postings.get(term).into_iter().flatten() {
+                if search.fields.contains(&posting.field) {
+                    *term_scores.entry(posting.id).or_insert(0) +=
+                        posting.count * posting.field.weight();
+                }
+            }
+
+            scores = Some(match scores {
+                None => term_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| term_scores.get(&id).map(|s| (id, score + s)))
+                    .collect(),
+            });
+        }
+
+        scores
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, score)| (score, id))
+            .collect()
+    }
+
+    fn add_postings(&mut self, id: usize, note: &Note) {
+        for field in SearchField::ALL.iter() {
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for token in field.tokens(note) {
+                *counts.entry(token).or_insert(0) += 1;
+            }
+
+            for (term, count) in counts {
+                self.postings.entry(term).or_default().push(Posting {
+                    id,
+                    field: *field,
+                    count,
+                });
+            }
+        }
+    }
+
+    fn remove_postings(&mut self, id: usize) {
+        for postings in self.postings.values_mut() {
+            postings.retain(|p| p.id != id);
+        }
+
+        self.postings.retain(|_, postings| !postings.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+
+    fn note(content: &str) -> Note {
+        NoteBuilder::new().with_content(content).build()
+    }
+
+    #[test]
+    fn incremental_updates_match_rebuild() {
+        let mut notes = vec![
+            note("Deploying the checklist"),
+            note("lunch with sam"),
+            note("deploy scripts for staging"),
+        ];
+        let mut index = SearchIndex::build(&notes);
+
+        notes.push(note("sam deploys on friday"));
+        index.push(&notes[3]);
+
+        notes[1] = note("lunch with alex");
+        index.update(1, &notes[1]);
+
+        notes.remove(0);
+        index.delete(0);
+
+        let rebuilt = SearchIndex::build(&notes);
+        for query in &["deploy", "sam", "lunch", "alex staging", "checklist"] {
+            let search = Search::new(query);
+            assert_eq!(index.search(&search), rebuilt.search(&search), "{}", query);
+        }
+        assert_eq!(index.len(), notes.len());
+    }
+
+    #[test]
+    fn search_requires_every_term() {
+        let notes = vec![note("deploy to staging"), note("deploy to production")];
+        let index = SearchIndex::build(&notes);
+
+        assert_eq!(
+            index.search(&Search::new("deploying staging")),
+            vec![(8, 0)]
+        );
+        assert!(index.search(&Search::new("the")).is_empty());
+    }
+}
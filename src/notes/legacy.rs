@@ -0,0 +1,50 @@
+//! Notes written by older versions of the file format.
+
+use serde::Deserialize;
+
+use crate::tags::*;
+
+use super::Note;
+
+/// A note as it was stored before the file format was versioned, which was
+/// before inline contexts and projects.
+#[derive(Deserialize)]
+pub struct LegacyNote {
+    created: String,
+    tags: Tags,
+    content: String,
+    desc: String,
+}
+
+impl From<LegacyNote> for Note {
+    fn from(note: LegacyNote) -> Note {
+        Note::new(note.created, note.tags, note.content, note.desc)
+    }
+}
+
+/// A note as it was stored in version 1 of the file format, before notes
+/// had a modified time.
+#[derive(Deserialize)]
+pub struct NoteV1 {
+    created: String,
+    tags: Tags,
+    content: String,
+    desc: String,
+    contexts: Vec<String>,
+    projects: Vec<String>,
+}
+
+impl From<NoteV1> for Note {
+    fn from(note: NoteV1) -> Note {
+        let mut new = Note::new(note.created, note.tags, note.content, note.desc);
+        new.contexts = note.contexts;
+        new.projects = note.projects;
+        new
+    }
+}
+
+/// The notes as they were stored in version 1 of the file format.
+#[derive(Deserialize)]
+pub struct NotesV1 {
+    pub notes: Vec<NoteV1>,
+}
@@ -1,10 +1,25 @@
 use chrono::{DateTime, Local};
 
+mod group;
+mod index;
+mod legacy;
 mod note;
+#[allow(clippy::module_inception)]
 mod notes;
+mod query;
+mod search;
+mod similarity;
+mod sort;
+mod stats;
 
+pub use group::GroupKey;
 pub use note::{Note, NoteBuilder, NoteWithId};
 pub use notes::Notes;
+pub use query::Query;
+pub use search::{Search, SearchField, SearchMatch};
+pub use similarity::RelatedNote;
+pub use sort::{AsNoteWithId, Listing, SortKey};
+pub use stats::{LongestNote, NoteStats, TagCount};
 
 fn format_time(time: &DateTime<Local>) -> String {
     time.format("%Y-%m-%d %H:%M:%S").to_string()
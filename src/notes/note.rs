@@ -2,8 +2,11 @@ use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
 use super::format_time;
+use crate::markers::Markers;
+use crate::registry::TagRegistry;
 use crate::tags::*;
 
+#[derive(Clone, Copy)]
 pub struct NoteWithId<'a>(pub usize, pub &'a Note);
 
 impl<'a> NoteWithId<'a> {
@@ -15,6 +18,10 @@ impl<'a> NoteWithId<'a> {
         &self.1.created
     }
 
+    pub fn modified(&self) -> &String {
+        &self.1.modified
+    }
+
     pub fn tags(&self) -> &Tags {
         &self.1.tags
     }
@@ -26,23 +33,67 @@ impl<'a> NoteWithId<'a> {
     pub fn desc(&self) -> &String {
         &self.1.desc
     }
+
+    pub fn title(&self) -> String {
+        self.1.title().to_string()
+    }
+
+    pub fn contexts(&self) -> &Vec<String> {
+        &self.1.contexts
+    }
+
+    pub fn projects(&self) -> &Vec<String> {
+        &self.1.projects
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Note {
     pub created: String,
+    pub modified: String,
     pub tags: Tags,
     pub content: String,
     pub desc: String,
+    pub contexts: Vec<String>,
+    pub projects: Vec<String>,
 }
 
 impl Note {
     pub fn new(created: String, tags: Tags, content: String, desc: String) -> Self {
         Note {
+            modified: created.clone(),
             created,
             tags,
             content,
             desc,
+            contexts: vec![],
+            projects: vec![],
+        }
+    }
+
+    /// Returns the first line of the note's content.
+    pub fn title(&self) -> &str {
+        self.content.lines().next().unwrap_or("").trim()
+    }
+
+    /// Adds inline markers to the note's tags, contexts, and projects.
+    pub fn add_markers(&mut self, markers: &Markers) {
+        for tag in markers.tags.tags() {
+            if !self.tags.0.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+
+        for context in markers.contexts.iter() {
+            if !self.contexts.contains(context) {
+                self.contexts.push(context.clone());
+            }
+        }
+
+        for project in markers.projects.iter() {
+            if !self.projects.contains(project) {
+                self.projects.push(project.clone());
+            }
         }
     }
 
@@ -52,13 +103,13 @@ impl Note {
     }
 
     /// Check if this note has a given tag.
-    pub fn has_tag(&self, tag: &Tag) -> bool {
-        self.tags.has_tag(tag)
+    pub fn has_tag(&self, tag: &Tag, registry: &TagRegistry) -> bool {
+        self.tags.has_tag(tag, registry)
     }
 
     /// Check if this note has all of the  given tags.
-    pub fn has_tags(&self, other: &Tags) -> bool {
-        self.tags.contains(other)
+    pub fn has_tags(&self, other: &Tags, registry: &TagRegistry) -> bool {
+        self.tags.contains(other, registry)
     }
 }
 
@@ -100,10 +151,10 @@ impl NoteBuilder {
     }
 
     pub fn build(self) -> Note {
-        let created = format_time(&self.created.unwrap_or_else(|| Local::now()));
+        let created = format_time(&self.created.unwrap_or_else(Local::now));
         let tags = self.tags.unwrap_or_else(|| Tags(vec![]));
-        let content = self.content.unwrap_or_else(|| "".to_string());
-        let desc = self.desc.unwrap_or_else(|| "".to_string());
+        let content = self.content.unwrap_or_default();
+        let desc = self.desc.unwrap_or_default();
 
         Note::new(created, tags, content, desc)
     }
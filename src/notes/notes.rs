@@ -2,51 +2,273 @@ use std::fs;
 use std::path::Path;
 
 use anyhow::Error;
+use chrono::{Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 
+use crate::dates::day;
 use crate::errors::NotesError;
+use crate::markers::Markers;
+use crate::queries::{SavedQueries, SavedQueryCount};
+use crate::registry::TagRegistry;
+use crate::rules::{TagChange, TagRules};
 use crate::tags::*;
+use crate::util::write_atomic;
 
-use super::note::{Note, NoteWithId};
+use super::index::SearchIndex;
+use super::legacy::{LegacyNote, NotesV1};
+use super::similarity::{duplicate_clusters, TfIdf};
+use super::{
+    format_time, LongestNote, Note, NoteStats, NoteWithId, Query, RelatedNote, Search, SearchMatch,
+    TagCount,
+};
 
+/// Marks the start of a versioned notes file. Files without it were written
+/// before the format was versioned and hold only the notes.
+const MAGIC: &[u8] = b"notes-rs";
+
+/// The version of the notes file format that is written.
+const FORMAT_VERSION: u16 = 2;
+
+/// The notes, along with the tag registry, auto-tagging rules, and saved
+/// queries that are stored next to them.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Notes(Vec<Note>);
+pub struct Notes {
+    notes: Vec<Note>,
+    #[serde(skip)]
+    registry: TagRegistry,
+    #[serde(skip)]
+    rules: TagRules,
+    #[serde(skip)]
+    queries: SavedQueries,
+    /// None if the search index is missing or stale.
+    #[serde(skip)]
+    index: Option<SearchIndex>,
+}
 
 impl Notes {
     /// Returns a new Notes given a Vec of Notes.
     pub fn new(notes: Vec<Note>) -> Self {
-        Notes(notes)
+        let index = Some(SearchIndex::build(&notes));
+
+        Notes {
+            notes,
+            registry: TagRegistry::default(),
+            rules: TagRules::default(),
+            queries: SavedQueries::default(),
+            index,
+        }
     }
 
     /// Attempts to read a given file and serialize it into a Notes
-    /// struct.
+    /// struct, along with the tag registry, rules, saved queries, and search
+    /// index stored next to it. Files written by older versions of the
+    /// format are upgraded.
     pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let buf = fs::read_to_string(path)?;
+        let buf = fs::read(&path)?;
+
+        let mut notes = if buf.starts_with(MAGIC) {
+            let version = bincode::deserialize::<u16>(&buf[MAGIC.len()..])
+                .map_err(|_| Error::new(NotesError::NoteDeserialization))?;
+            let body = &buf[MAGIC.len() + 2..];
 
-        bincode::deserialize::<Notes>(buf.as_bytes())
-            .map_err(|_| Error::new(NotesError::NoteDeserialization))
+            match version {
+                1 => {
+                    let v1 = bincode::deserialize::<NotesV1>(body)
+                        .map_err(|_| Error::new(NotesError::NoteDeserialization))?;
+
+                    Notes::new(v1.notes.into_iter().map(Note::from).collect())
+                }
+                FORMAT_VERSION => bincode::deserialize::<Notes>(body)
+                    .map_err(|_| Error::new(NotesError::NoteDeserialization))?,
+                _ => return Err(Error::new(NotesError::UnsupportedFormat(version))),
+            }
+        } else {
+            let legacy = bincode::deserialize::<Vec<LegacyNote>>(&buf)
+                .map_err(|_| Error::new(NotesError::NoteDeserialization))?;
+
+            Notes::new(legacy.into_iter().map(Note::from).collect())
+        };
+        notes.registry = TagRegistry::from_file(TagRegistry::path_for(&path))?;
+        notes.rules = TagRules::from_file(TagRules::path_for(&path))?;
+        notes.queries = SavedQueries::from_file(SavedQueries::path_for(&path))?;
+        notes.index = SearchIndex::from_file(&path, notes.len())?;
+
+        Ok(notes)
     }
 
     /// Serializes the structure to bytes and writes it to the
-    /// given file.
+    /// given file. The notes file and the files next to it are written
+    /// atomically. A missing or stale search index is rebuilt.
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
-        let serialized = bincode::serialize(self)?;
+        let mut serialized = MAGIC.to_vec();
+        serialized.extend(bincode::serialize(&(FORMAT_VERSION, self))?);
 
-        fs::write(path, serialized.as_slice())?;
+        write_atomic(&path, serialized.as_slice())?;
 
-        Ok(())
+        self.registry.to_file(TagRegistry::path_for(&path))?;
+        self.rules.to_file(TagRules::path_for(&path))?;
+        self.queries.to_file(SavedQueries::path_for(&path))?;
+
+        match &self.index {
+            Some(index) => index.to_file(&path),
+            None => SearchIndex::build(&self.notes).to_file(&path),
+        }
+    }
+
+    /// Checks whether the search index is up to date. Searches still work
+    /// without it, but have to read every note.
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Rebuilds the search index from scratch. Returns the number of notes
+    /// indexed.
+    pub fn reindex(&mut self) -> usize {
+        self.index = Some(SearchIndex::build(&self.notes));
+
+        self.len()
+    }
+
+    /// Updates the search index after the note at the given index changes.
+    fn update_index(&mut self, index: usize) {
+        if let Some(search_index) = &mut self.index {
+            search_index.update(index, &self.notes[index]);
+        }
+    }
+
+    /// Returns the tag registry.
+    pub fn registry(&self) -> &TagRegistry {
+        &self.registry
+    }
+
+    /// Returns the tag registry for modification.
+    pub fn registry_mut(&mut self) -> &mut TagRegistry {
+        &mut self.registry
+    }
+
+    /// Returns the auto-tagging rules.
+    pub fn rules(&self) -> &TagRules {
+        &self.rules
+    }
+
+    /// Returns the auto-tagging rules for modification.
+    pub fn rules_mut(&mut self) -> &mut TagRules {
+        &mut self.rules
+    }
+
+    /// Returns the saved queries.
+    pub fn queries(&self) -> &SavedQueries {
+        &self.queries
+    }
+
+    /// Returns the saved queries for modification.
+    pub fn queries_mut(&mut self) -> &mut SavedQueries {
+        &mut self.queries
+    }
+
+    /// Returns every saved query along with the number of notes it currently
+    /// matches, or the error of a query that no longer parses, unless there
+    /// are no saved queries, in which case it returns None.
+    pub fn get_saved_query_counts(&self) -> Option<Vec<SavedQueryCount<'_>>> {
+        if self.queries.get_all().is_empty() {
+            return None;
+        }
+
+        let counts = self
+            .queries
+            .get_all()
+            .iter()
+            .map(|saved| {
+                let count = Query::parse(&saved.expr)
+                    .map(|query| self.query(&query).count())
+                    .map_err(|e| e.to_string());
+
+                SavedQueryCount(saved, count)
+            })
+            .collect();
+
+        Some(counts)
+    }
+
+    /// Adds the tags of every matching auto-tagging rule to a note. `file` is
+    /// the file the note was created from, if any. Returns the tags that were
+    /// added.
+    pub fn apply_rules(&self, note: &mut Note, file: Option<&Path>) -> Tags {
+        let mut added = Tags(vec![]);
+
+        for tag in self.rules.tags_for(&note.content, file).0 {
+            if !note.has_tag(&tag, &self.registry) {
+                note.tags.push(tag.clone());
+                added.push(tag);
+            }
+        }
+
+        added
+    }
+
+    /// Returns the tags that the auto-tagging rules would add to each note,
+    /// unless no note would change, in which case it returns None.
+    pub fn get_retag_changes(&self) -> anyhow::Result<Option<Vec<TagChange>>> {
+        let mut changes = vec![];
+
+        for (i, note) in self.notes.iter().enumerate() {
+            let added = self.apply_rules(&mut note.clone(), None);
+
+            if !added.0.is_empty() {
+                changes.push(TagChange {
+                    id: i,
+                    content: note.content.clone(),
+                    added,
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(changes))
+    }
+
+    /// Re-applies the auto-tagging rules to every note. Returns the changes
+    /// that were made.
+    pub fn retag(&mut self) -> anyhow::Result<Option<Vec<TagChange>>> {
+        let changes = self.get_retag_changes()?;
+
+        if let Some(changes) = &changes {
+            for change in changes {
+                self.notes[change.id].tags.0.extend(change.added.0.clone());
+                self.update_index(change.id);
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Adds inline markers to the note at the given index. If the index is
+    /// invalid, this returns an error.
+    pub fn add_markers(&mut self, index: usize, markers: &Markers) -> anyhow::Result<&Note> {
+        let note = self
+            .notes
+            .get_mut(index)
+            .ok_or_else(|| Error::new(NotesError::InvalidNoteId(index)))?;
+
+        note.add_markers(markers);
+        self.update_index(index);
+
+        Ok(&self.notes[index])
     }
 
     /// Returns the length of the underlying Vec.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.notes.len()
     }
 
     /// Returns a slice of all of the notes, unless there are no notes,
     /// in which case it returns None.
     pub fn get_all(&self) -> Option<&[Note]> {
         if self.len() > 0 {
-            Some(self.0.as_slice())
+            Some(self.notes.as_slice())
         } else {
             None
         }
@@ -54,60 +276,265 @@ impl Notes {
 
     /// Returns a Vec of note references and their corresponding
     /// index.
-    pub fn get_all_with_id(&self) -> Option<Vec<NoteWithId>> {
-        if self.0.is_empty() {
+    pub fn get_all_with_id(&self) -> Option<Vec<NoteWithId<'_>>> {
+        if self.notes.is_empty() {
             return None;
         }
 
-        let mut notes = vec![];
-
-        for (i, note) in self.0.iter().enumerate() {
-            notes.push(NoteWithId(i, note));
-        }
+        Some(self.iter_with_id().collect())
+    }
 
-        Some(notes)
+    /// Returns an iterator over note references and their corresponding
+    /// index, in insertion order.
+    pub fn iter_with_id(&self) -> impl Iterator<Item = NoteWithId<'_>> {
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| NoteWithId(i, note))
     }
 
     /// Gets the note at the given index if it is within bounds; otherwise,
     /// it returns None.
     pub fn get(&self, index: usize) -> Option<&Note> {
-        self.0.get(index)
+        self.notes.get(index)
     }
 
     /// Gets the note and its ID at the given index if it is within bounds;
     /// otherwise, it returns None.
-    pub fn get_with_id(&self, index: usize) -> Option<NoteWithId> {
-        self.0.get(index).map(|n| NoteWithId(index, n))
+    pub fn get_with_id(&self, index: usize) -> Option<NoteWithId<'_>> {
+        self.notes.get(index).map(|n| NoteWithId(index, n))
     }
 
-    /// Gets all notes that have a given tag.
-    pub fn get_all_with_tag(&self, tag: Tag) -> Option<Vec<NoteWithId>> {
-        self.get_all_with_tags(Tags(vec![tag]))
+    /// Returns an iterator over the notes that match a query, in insertion
+    /// order. Notes are only checked as the iterator is advanced.
+    pub fn query<'a: 'b, 'b>(
+        &'a self,
+        query: &'b Query,
+    ) -> impl Iterator<Item = NoteWithId<'a>> + 'b {
+        self.iter_with_id()
+            .filter(move |note| query.matches(note, &self.registry))
     }
 
-    /// Gets all notes that possess all of the listed tags.
-    pub fn get_all_with_tags(&self, tags: Tags) -> Option<Vec<NoteWithId>> {
-        if self.0.is_empty() {
+    /// Gets all notes that match a search, most relevant first, along with
+    /// where they matched, unless none match, in which case it returns None.
+    /// Uses the search index for term searches when it is up to date.
+    pub fn search(&self, search: &Search) -> Option<Vec<SearchMatch<'_>>> {
+        let candidates: Vec<usize> = match &self.index {
+            Some(index) if search.is_indexable() => {
+                index.search(search).into_iter().map(|(_, id)| id).collect()
+            }
+            _ => (0..self.notes.len()).collect(),
+        };
+
+        let mut matches: Vec<SearchMatch<'_>> = candidates
+            .into_iter()
+            .filter_map(|id| {
+                let note = &self.notes[id];
+                search.matches(note).map(|(score, spans)| SearchMatch {
+                    note: NoteWithId(id, note),
+                    score,
+                    spans,
+                })
+            })
+            .collect();
+
+        if matches.is_empty() {
             return None;
         }
 
-        let mut notes = vec![];
-        for (i, note) in self.0.iter().enumerate() {
-            if note.has_tags(&tags) {
-                notes.push(NoteWithId(i, note));
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.id().cmp(&b.id())));
+
+        Some(matches)
+    }
+
+    /// Gets the notes most similar to the note at the given index, by the
+    /// cosine similarity of their TF-IDF vectors, unless no note shares a
+    /// term with it, in which case it returns None.
+    pub fn related(&self, index: usize) -> anyhow::Result<Option<Vec<RelatedNote<'_>>>> {
+        if index >= self.notes.len() {
+            return Err(Error::new(NotesError::InvalidNoteId(index)));
+        }
+
+        let related: Vec<RelatedNote<'_>> = TfIdf::build(&self.notes)
+            .related(index)
+            .into_iter()
+            .map(|(score, id)| RelatedNote {
+                note: NoteWithId(id, &self.notes[id]),
+                score,
+            })
+            .collect();
+
+        if related.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(related))
+    }
+
+    /// Gets each group of notes whose content is at least `threshold` similar,
+    /// from 0 to 1, unless there are none, in which case it returns None.
+    pub fn get_duplicates(&self, threshold: f64) -> Option<Vec<Vec<NoteWithId<'_>>>> {
+        let clusters: Vec<Vec<NoteWithId<'_>>> = duplicate_clusters(&self.notes, threshold)
+            .into_iter()
+            .map(|ids| {
+                ids.into_iter()
+                    .map(|id| NoteWithId(id, &self.notes[id]))
+                    .collect()
+            })
+            .collect();
+
+        if clusters.is_empty() {
+            return None;
+        }
+
+        Some(clusters)
+    }
+
+    /// Returns the usage of every tag, ordered by tag name, unless no note has
+    /// a tag, in which case it returns None. Aliases are counted as the tag
+    /// they stand for.
+    pub fn get_tag_usage(&self) -> Option<Vec<TagUsage>> {
+        let mut usage: Vec<TagUsage> = vec![];
+
+        for note in self.notes.iter() {
+            for tag in note.tags.tags() {
+                let tag = self.registry.resolve(tag);
+                if let Some(entry) = usage.iter_mut().find(|u| &u.tag == tag) {
+                    entry.count += 1;
+                    if note.created > entry.last_used {
+                        entry.last_used = note.created.clone();
+                    }
+                } else {
+                    usage.push(TagUsage {
+                        tag: tag.clone(),
+                        count: 1,
+                        last_used: note.created.clone(),
+                        desc: self
+                            .registry
+                            .get(tag)
+                            .map(|r| r.desc.clone())
+                            .unwrap_or_default(),
+                    });
+                }
             }
         }
 
-        if notes.is_empty() {
+        if usage.is_empty() {
             return None;
         }
 
-        Some(notes)
+        usage.sort_by(|a, b| a.tag.cmp(&b.tag));
+
+        Some(usage)
+    }
+
+    /// Summarizes the notes stored at the given path. The creation rate is
+    /// counted over the weeks from the first note up to today, and at least
+    /// one week.
+    pub fn stats<P: AsRef<Path>>(&self, path: P, today: NaiveDate) -> NoteStats {
+        let mut tags: Vec<TagCount> = self
+            .get_tag_usage()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|usage| TagCount {
+                tag: usage.tag.to_string(),
+                notes: usage.count,
+            })
+            .collect();
+        tags.sort_by_key(|count| std::cmp::Reverse(count.notes));
+
+        let lengths: Vec<usize> = self
+            .notes
+            .iter()
+            .map(|note| note.content.chars().count())
+            .collect();
+        let longest = lengths
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, length)| **length)
+            .map(|(id, length)| LongestNote {
+                id,
+                length: *length,
+            });
+
+        let days = self
+            .notes
+            .iter()
+            .filter_map(|note| day(&note.created))
+            .min()
+            .map_or(0, |first| (today - first).num_days() + 1);
+        let weeks = (days as f64 / 7.0).max(1.0);
+
+        let store_size = [
+            path.as_ref().to_path_buf(),
+            TagRegistry::path_for(&path),
+            TagRules::path_for(&path),
+            SavedQueries::path_for(&path),
+            SearchIndex::path_for(&path),
+        ]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+        NoteStats {
+            notes: self.notes.len(),
+            untagged: self
+                .notes
+                .iter()
+                .filter(|note| note.tags.0.is_empty())
+                .count(),
+            tags,
+            average_length: match lengths.len() {
+                0 => 0.0,
+                n => lengths.iter().sum::<usize>() as f64 / n as f64,
+            },
+            longest,
+            notes_per_week: self.notes.len() as f64 / weeks,
+            store_size,
+        }
+    }
+
+    /// Renames a tag on every note that has it, along with its registry
+    /// record. Returns the number of notes changed, or an error if no note has
+    /// the tag.
+    pub fn rename_tag(&mut self, old: Tag, new: Tag) -> anyhow::Result<usize> {
+        let changed = self.merge_tags(Tags(vec![old.clone()]), new.clone())?;
+
+        self.registry.rename(&old, new);
+
+        Ok(changed)
+    }
+
+    /// Replaces all of the given tags with a single tag on every note. Either
+    /// every note is rewritten or, if one of the tags is unused, none are.
+    /// Returns the number of notes changed.
+    pub fn merge_tags(&mut self, tags: Tags, into: Tag) -> anyhow::Result<usize> {
+        for tag in tags.tags() {
+            if !self.notes.iter().any(|note| note.tags.tags().contains(tag)) {
+                return Err(Error::new(NotesError::TagNotFound(tag.to_string())));
+            }
+        }
+
+        let mut changed = 0;
+        for i in 0..self.len() {
+            if self.notes[i].tags.replace(&tags, &into) {
+                self.update_index(i);
+                changed += 1;
+            }
+        }
+
+        Ok(changed)
     }
 
     /// Pushes a new note onto the Vec and returns the note ID.
     pub fn push(&mut self, note: Note) -> usize {
-        self.0.push(note);
+        if let Some(index) = &mut self.index {
+            index.push(&note);
+        }
+
+        self.notes.push(note);
 
         self.len() - 1
     }
@@ -118,12 +545,74 @@ impl Notes {
         if index >= self.len() {
             Err(Error::new(NotesError::InvalidNoteId(index)))
         } else {
-            Ok(self.0.remove(index))
+            if let Some(search_index) = &mut self.index {
+                search_index.delete(index);
+            }
+
+            Ok(self.notes.remove(index))
         }
     }
 
-    /// Edits a note's content or tags without changing the creation time. If the index is
-    /// invalid, this returns an error.
+    /// Merges notes into the one with the lowest id and deletes the others.
+    /// The merged note has the union of their tags, contexts and projects,
+    /// their distinct contents and descriptions joined by blank lines, and
+    /// the earliest creation time. Returns the id of the merged note. Notes
+    /// after a deleted note move down, as with `delete`.
+    pub fn merge(&mut self, ids: &[usize]) -> anyhow::Result<usize> {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+
+        if let Some(id) = ids.iter().find(|id| **id >= self.len()) {
+            return Err(Error::new(NotesError::InvalidNoteId(*id)));
+        }
+
+        let (target, others) = match ids.split_first() {
+            Some((target, others)) if !others.is_empty() => (*target, others),
+            _ => return Err(Error::new(NotesError::NothingToMerge)),
+        };
+
+        let mut merged = self.notes[target].clone();
+
+        for id in others {
+            let note = &self.notes[*id];
+
+            if !merged.content.contains(note.content.as_str()) {
+                merged.content = format!("{}\n\n{}", merged.content, note.content);
+            }
+
+            if merged.desc.is_empty() {
+                merged.desc = note.desc.clone();
+            } else if !note.desc.is_empty() && !merged.desc.contains(note.desc.as_str()) {
+                merged.desc = format!("{}\n\n{}", merged.desc, note.desc);
+            }
+
+            if note.created < merged.created {
+                merged.created = note.created.clone();
+            }
+
+            merged.add_markers(&Markers {
+                tags: note.tags.clone(),
+                contexts: note.contexts.clone(),
+                projects: note.projects.clone(),
+            });
+        }
+
+        merged.modified = format_time(&Local::now());
+        self.notes[target] = merged;
+        self.update_index(target);
+
+        for id in others.iter().rev() {
+            self.delete(*id)?;
+        }
+
+        Ok(target)
+    }
+
+    /// Edits a note's content or tags without changing the creation time. The
+    /// auto-tagging rules are applied again only when the content changes, so
+    /// that tags they added can be removed. If the index is invalid, this
+    /// returns an error.
     pub fn edit(
         &mut self,
         index: usize,
@@ -131,23 +620,235 @@ impl Notes {
         tags: Option<Tags>,
         desc: Option<String>,
     ) -> anyhow::Result<&Note> {
-        if let Some(note) = self.0.get_mut(index) {
-            if let Some(content) = content {
-                note.content = content;
-            }
+        let mut note = self
+            .notes
+            .get(index)
+            .cloned()
+            .ok_or_else(|| Error::new(NotesError::InvalidNoteId(index)))?;
 
-            if let Some(tags) = tags {
-                note.tags = tags;
-            }
+        if let Some(tags) = tags {
+            note.tags = tags;
+        }
 
-            if let Some(desc) = desc {
-                note.desc = desc;
-            }
+        if let Some(content) = content {
+            note.content = content;
+            self.apply_rules(&mut note, None);
+        }
 
-            // This is safe, as we just confirmed this index exists.
-            Ok(self.get(index).unwrap())
-        } else {
-            Err(Error::new(NotesError::InvalidNoteId(index)))
+        if let Some(desc) = desc {
+            note.desc = desc;
+        }
+
+        note.modified = format_time(&Local::now());
+        self.notes[index] = note;
+        self.update_index(index);
+
+        Ok(&self.notes[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::queries::SavedQuery;
+    use chrono::TimeZone;
+    use serde::Serialize;
+    use tempfile::tempdir;
+
+    #[derive(Serialize)]
+    struct UnversionedNote {
+        created: String,
+        tags: Tags,
+        content: String,
+        desc: String,
+    }
+
+    #[test]
+    fn read_unversioned_file() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("notes");
+
+        let unversioned = vec![UnversionedNote {
+            created: "2020-06-01 12:00:00".to_string(),
+            tags: Tags::from(vec!["old"]),
+            content: "Written before the format was versioned.".to_string(),
+            desc: "".to_string(),
+        }];
+        fs::write(&path, bincode::serialize(&unversioned)?)?;
+
+        let notes = Notes::from_file(&path)?;
+        let note = notes.get(0).unwrap();
+        assert_eq!(note.content, "Written before the format was versioned.");
+        assert!(note.contexts.is_empty());
+
+        notes.to_file(&path)?;
+        assert!(fs::read(&path)?.starts_with(MAGIC));
+        assert_eq!(Notes::from_file(&path)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[derive(Serialize)]
+    struct NoteV1 {
+        created: String,
+        tags: Tags,
+        content: String,
+        desc: String,
+        contexts: Vec<String>,
+        projects: Vec<String>,
+    }
+
+    #[test]
+    fn read_version_1_file() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("notes");
+
+        let notes = vec![NoteV1 {
+            created: "2020-06-01 12:00:00".to_string(),
+            tags: Tags::from(vec!["old"]),
+            content: "Written before notes had a modified time.".to_string(),
+            desc: "".to_string(),
+            contexts: vec!["work".to_string()],
+            projects: vec![],
+        }];
+        let mut buf = MAGIC.to_vec();
+        buf.extend(bincode::serialize(&(1u16, notes))?);
+        fs::write(&path, buf)?;
+
+        let notes = Notes::from_file(&path)?;
+        let note = notes.get(0).unwrap();
+        assert_eq!(note.modified, "2020-06-01 12:00:00");
+        assert_eq!(note.contexts, vec!["work".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_notes() -> anyhow::Result<()> {
+        let mut notes = Notes::new(vec![]);
+        for (content, tags, desc) in [
+            ("Call the bank", vec!["money"], ""),
+            ("lunch", vec![], ""),
+            ("Call the bank", vec!["calls"], "before noon"),
+            ("Ask about the rate", vec!["money"], ""),
+        ] {
+            notes.push(
+                NoteBuilder::new()
+                    .with_content(content)
+                    .with_tags(Tags::from(tags))
+                    .with_desc(desc)
+                    .build(),
+            );
         }
+
+        assert_eq!(notes.merge(&[3, 0, 2])?, 0);
+        assert_eq!(notes.len(), 2);
+
+        let merged = notes.get(0).unwrap();
+        assert_eq!(merged.content, "Call the bank\n\nAsk about the rate");
+        assert_eq!(merged.desc, "before noon");
+        assert_eq!(merged.tags, Tags::from(vec!["money", "calls"]));
+        assert_eq!(notes.get(1).unwrap().content, "lunch");
+        assert_eq!(
+            notes.search(&Search::new("rate")).unwrap()[0].id(),
+            0,
+            "the search index follows the merge"
+        );
+
+        assert!(notes.merge(&[1, 1]).is_err());
+        assert!(notes.merge(&[0, 5]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn note_stats() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("notes");
+        let time = |d: u32| Local.ymd(2026, 1, d).and_hms(12, 0, 0);
+
+        let mut notes = Notes::new(vec![]);
+        notes.push(
+            NoteBuilder::new()
+                .with_time(time(1))
+                .with_content("Deploy")
+                .with_tags(Tags::from(vec!["ops", "rust"]))
+                .build(),
+        );
+        notes.push(
+            NoteBuilder::new()
+                .with_time(time(3))
+                .with_content("Cargo build")
+                .with_tags(Tags::from(vec!["rust"]))
+                .build(),
+        );
+        notes.push(
+            NoteBuilder::new()
+                .with_time(time(8))
+                .with_content("Plain")
+                .build(),
+        );
+        notes.to_file(&path)?;
+
+        let stats = notes.stats(&path, NaiveDate::from_ymd(2026, 1, 14));
+        assert_eq!(stats.notes, 3);
+        assert_eq!(stats.untagged, 1);
+        assert_eq!(
+            stats.tags,
+            vec![
+                TagCount {
+                    tag: "rust".to_string(),
+                    notes: 2
+                },
+                TagCount {
+                    tag: "ops".to_string(),
+                    notes: 1
+                },
+            ]
+        );
+        assert_eq!(stats.average_length, 22.0 / 3.0);
+        assert_eq!(stats.longest, Some(LongestNote { id: 1, length: 11 }));
+        assert_eq!(stats.notes_per_week, 1.5);
+        assert!(stats.store_size >= fs::metadata(&path)?.len());
+
+        assert_eq!(
+            Notes::new(vec![]).stats(dir.path().join("none"), NaiveDate::from_ymd(2026, 1, 1)),
+            NoteStats::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_saved_queries() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("notes");
+
+        Notes::new(vec![NoteBuilder::new()
+            .with_content("Deploy staging")
+            .with_tags(Tags::from(vec!["ops"]))
+            .build()])
+        .to_file(&path)?;
+
+        // The second query was saved before `0d` stopped being a date.
+        let saved = vec![
+            SavedQuery {
+                name: "ops".to_string(),
+                expr: "tag:ops".to_string(),
+            },
+            SavedQuery {
+                name: "today".to_string(),
+                expr: "created>0d".to_string(),
+            },
+        ];
+        fs::write(SavedQueries::path_for(&path), bincode::serialize(&saved)?)?;
+
+        let notes = Notes::from_file(&path)?;
+        let counts = notes.get_saved_query_counts().unwrap();
+        assert_eq!(counts[0].notes(), "1");
+        assert!(counts[1].notes().starts_with("error: "));
+
+        Ok(())
     }
 }
@@ -0,0 +1,404 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use chrono::{Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use crate::dates::{day, parse_date, TimeField, TimeFilter};
+use crate::errors::NotesError;
+use crate::registry::TagRegistry;
+use crate::tags::*;
+
+use super::NoteWithId;
+
+/// A note field that a query can filter on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryField {
+    Id,
+    Tag,
+    Context,
+    Project,
+    Created,
+    Modified,
+    Title,
+    Content,
+    Desc,
+    Length,
+}
+
+impl FromStr for QueryField {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "id" => Ok(QueryField::Id),
+            "tag" | "tags" => Ok(QueryField::Tag),
+            "context" => Ok(QueryField::Context),
+            "project" => Ok(QueryField::Project),
+            "created" => Ok(QueryField::Created),
+            "modified" => Ok(QueryField::Modified),
+            "title" => Ok(QueryField::Title),
+            "content" => Ok(QueryField::Content),
+            "desc" => Ok(QueryField::Desc),
+            "length" => Ok(QueryField::Length),
+            _ => Err(anyhow!(
+                "unknown field `{}`; expected one of id, tag, context, project, created, \
+                 modified, title, content, desc, length",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for QueryField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// How a condition compares a field with its value. `Is` checks that a
+/// note has a tag, context or project, that text contains the value, or that
+/// a number or day is equal to it. `Matches` applies a regular expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryOp {
+    Is,
+    Matches,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl QueryOp {
+    const ALL: [(&'static str, QueryOp); 6] = [
+        (">=", QueryOp::Ge),
+        ("<=", QueryOp::Le),
+        (":", QueryOp::Is),
+        ("~", QueryOp::Matches),
+        (">", QueryOp::Gt),
+        ("<", QueryOp::Lt),
+    ];
+
+    fn compare(self, ordering: Ordering) -> bool {
+        match self {
+            QueryOp::Is => ordering == Ordering::Equal,
+            QueryOp::Lt => ordering == Ordering::Less,
+            QueryOp::Le => ordering != Ordering::Greater,
+            QueryOp::Gt => ordering == Ordering::Greater,
+            QueryOp::Ge => ordering != Ordering::Less,
+            QueryOp::Matches => false,
+        }
+    }
+}
+
+/// The value of a condition, parsed for its field and operator.
+#[derive(Debug, Clone)]
+enum QueryValue {
+    Number(usize),
+    Day(NaiveDate),
+    Text(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: QueryField,
+    op: QueryOp,
+    value: QueryValue,
+    negated: bool,
+}
+
+impl Condition {
+    fn matches(&self, note: &NoteWithId<'_>, registry: &TagRegistry) -> bool {
+        let texts = |field: QueryField| -> Vec<String> {
+            match field {
+                QueryField::Tag => note.tags().0.iter().map(String::from).collect(),
+                QueryField::Context => note.contexts().clone(),
+                QueryField::Project => note.projects().clone(),
+                QueryField::Title => vec![note.title()],
+                QueryField::Content => vec![note.content().clone()],
+                QueryField::Desc => vec![note.desc().clone()],
+                _ => vec![],
+            }
+        };
+
+        let matched = match (&self.value, self.field) {
+            (QueryValue::Number(n), QueryField::Id) => self.op.compare(note.id().cmp(n)),
+            (QueryValue::Number(n), _) => self.op.compare(note.content().chars().count().cmp(n)),
+            (QueryValue::Day(d), field) => {
+                let time = if field == QueryField::Modified {
+                    note.modified()
+                } else {
+                    note.created()
+                };
+                day(time).is_some_and(|day| self.op.compare(day.cmp(d)))
+            }
+            (QueryValue::Regex(regex), field) => texts(field).iter().any(|t| regex.is_match(t)),
+            (QueryValue::Text(tag), QueryField::Tag) => {
+                note.tags().has_tag(&Tag::from(tag.as_str()), registry)
+            }
+            (QueryValue::Text(name), QueryField::Context | QueryField::Project) => {
+                texts(self.field)
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(name))
+            }
+            (QueryValue::Text(text), field) => texts(field)
+                .iter()
+                .any(|t| t.to_lowercase().contains(text.as_str())),
+        };
+
+        matched != self.negated
+    }
+}
+
+/// A filter on notes that combines conditions on any of their fields. Notes
+/// match when they meet every condition.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    conditions: Vec<Condition>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Parses the query syntax, such as
+    /// `tag:rust created>2026-01-01 content~"deploy" -tag:done`. Each term
+    /// is a field, an operator, and a value, and may be negated with a
+    /// leading `-`. Values with spaces are quoted. Terms without a field
+    /// search the content.
+    pub fn parse(query: &str) -> anyhow::Result<Self> {
+        let mut parsed = Query::new();
+
+        for (term, quoted) in split_terms(query)? {
+            let (negated, rest) = match term.strip_prefix('-') {
+                Some(rest) if !quoted && !rest.is_empty() => (true, rest),
+                _ => (false, term.as_str()),
+            };
+
+            let field_end = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let op = QueryOp::ALL
+                .iter()
+                .find(|(op, _)| field_end > 0 && rest[field_end..].starts_with(op));
+
+            parsed = match op {
+                Some((op_text, op)) if !quoted => {
+                    let field = rest[..field_end]
+                        .parse::<QueryField>()
+                        .map_err(|e| invalid(&term, &e.to_string()))?;
+                    let value = &rest[field_end + op_text.len()..];
+
+                    parsed
+                        .condition(field, *op, value, negated)
+                        .map_err(|e| invalid(&term, &e.to_string()))?
+                }
+                _ => parsed.condition(QueryField::Content, QueryOp::Is, rest, negated)?,
+            };
+        }
+
+        Ok(parsed)
+    }
+
+    /// Adds a condition on a field. The value is parsed for the field: a
+    /// number for ids and lengths, a day for created and modified times, and
+    /// a regular expression for `Matches`.
+    pub fn condition(
+        mut self,
+        field: QueryField,
+        op: QueryOp,
+        value: &str,
+        negated: bool,
+    ) -> anyhow::Result<Self> {
+        let value = match (field, op) {
+            (QueryField::Id | QueryField::Length, QueryOp::Matches) => {
+                return Err(anyhow!("`{}` cannot be matched with `~`", field));
+            }
+            (QueryField::Id | QueryField::Length, _) => QueryValue::Number(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("`{}` is not a number", value))?,
+            ),
+            (QueryField::Created | QueryField::Modified, QueryOp::Matches) => {
+                return Err(anyhow!("`{}` cannot be matched with `~`", field));
+            }
+            (QueryField::Created | QueryField::Modified, _) => {
+                QueryValue::Day(parse_date(value, Local::today().naive_local())?)
+            }
+            (_, QueryOp::Matches) => {
+                QueryValue::Regex(RegexBuilder::new(value).case_insensitive(true).build()?)
+            }
+            (QueryField::Tag, QueryOp::Is) => QueryValue::Text(value.to_string()),
+            (_, QueryOp::Is) => QueryValue::Text(value.to_lowercase()),
+            (_, _) => {
+                return Err(anyhow!("`{}` cannot be compared with `<` or `>`", field));
+            }
+        };
+
+        self.conditions.push(Condition {
+            field,
+            op,
+            value,
+            negated,
+        });
+
+        Ok(self)
+    }
+
+    /// Only matches the note with the given id.
+    pub fn id(mut self, id: usize) -> Self {
+        self.conditions.push(Condition {
+            field: QueryField::Id,
+            op: QueryOp::Is,
+            value: QueryValue::Number(id),
+            negated: false,
+        });
+        self
+    }
+
+    /// Only matches notes that have all of the given tags or their aliases.
+    pub fn tags(mut self, tags: &Tags) -> Self {
+        for tag in tags.tags() {
+            self.conditions.push(Condition {
+                field: QueryField::Tag,
+                op: QueryOp::Is,
+                value: QueryValue::Text(String::from(tag)),
+                negated: false,
+            });
+        }
+        self
+    }
+
+    /// Only matches notes whose created or modified day is within the filter.
+    pub fn time(mut self, time: &TimeFilter) -> Self {
+        let field = match time.field {
+            TimeField::Created => QueryField::Created,
+            TimeField::Modified => QueryField::Modified,
+        };
+
+        for (op, day) in [(QueryOp::Ge, time.since), (QueryOp::Le, time.until)].iter() {
+            if let Some(day) = day {
+                self.conditions.push(Condition {
+                    field,
+                    op: *op,
+                    value: QueryValue::Day(*day),
+                    negated: false,
+                });
+            }
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// Checks whether a note meets every condition.
+    pub fn matches(&self, note: &NoteWithId<'_>, registry: &TagRegistry) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(note, registry))
+    }
+}
+
+fn invalid(term: &str, reason: &str) -> Error {
+    let reason = reason.trim_end_matches('.').to_string();
+    Error::new(NotesError::InvalidQuery(term.to_string(), reason))
+}
+
+/// Splits a query into terms at whitespace outside of double quotes, and
+/// removes the quotes. Also returns whether each term started with a quote,
+/// in which case it is taken literally.
+fn split_terms(query: &str) -> anyhow::Result<Vec<(String, bool)>> {
+    let mut terms = vec![];
+    let mut term: Option<(String, bool)> = None;
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                term.get_or_insert_with(|| (String::new(), true));
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => terms.extend(term.take()),
+            c => term.get_or_insert_with(|| (String::new(), false)).0.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(invalid(query, "a quote is never closed"));
+    }
+
+    terms.extend(term);
+
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+
+    #[test]
+    fn parse_and_match() -> anyhow::Result<()> {
+        let registry = TagRegistry::default();
+        let notes = [
+            NoteBuilder::new()
+                .with_content("Deploy the staging server")
+                .with_tags(Tags::from(vec!["rust", "ops"]))
+                .build(),
+            NoteBuilder::new()
+                .with_content("Deploy done")
+                .with_tags(Tags::from(vec!["rust", "done"]))
+                .build(),
+            NoteBuilder::new()
+                .with_content("lunch")
+                .with_tags(Tags::from(vec!["rust"]))
+                .build(),
+        ];
+        let ids = |query: &str| -> anyhow::Result<Vec<usize>> {
+            let query = Query::parse(query)?;
+            Ok(notes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| NoteWithId(i, n))
+                .filter(|n| query.matches(n, &registry))
+                .map(|n| n.id())
+                .collect())
+        };
+
+        assert_eq!(
+            ids(r#"tag:rust created>2020-01-01 content~"^deploy" -tag:done"#)?,
+            vec![0]
+        );
+        assert_eq!(ids(r#""staging server""#)?, vec![0]);
+        assert_eq!(ids("id>=1 length<12")?, vec![1, 2]);
+        assert_eq!(ids("-deploy")?, vec![2]);
+        assert_eq!(ids("")?, vec![0, 1, 2]);
+
+        assert!(Query::parse("tga:rust").is_err());
+        assert!(Query::parse("id:one").is_err());
+        assert!(Query::parse("tag>rust").is_err());
+        assert!(Query::parse("content:\"deploy").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_quoted_terms() -> anyhow::Result<()> {
+        assert_eq!(
+            split_terms(r#"tag:rust  content~"deploy now" "-literal""#)?,
+            vec![
+                ("tag:rust".to_string(), false),
+                ("content~deploy now".to_string(), false),
+                ("-literal".to_string(), true),
+            ]
+        );
+
+        Ok(())
+    }
+}
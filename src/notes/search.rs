@@ -0,0 +1,347 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::Error;
+use regex::{Regex, RegexBuilder};
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use strsim::damerau_levenshtein;
+
+use crate::dates::TimeFilter;
+use crate::display::Highlighted;
+use crate::errors::NotesError;
+use crate::tags::*;
+
+use super::{Note, NoteWithId};
+
+/// Common words that are left out of searches and the search index.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "in",
+    "into", "is", "it", "its", "of", "on", "or", "that", "the", "their", "then", "there", "these",
+    "this", "to", "was", "were", "will", "with",
+];
+
+/// Returns the words of some text along with their byte ranges.
+fn words(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            words.push((s..i, &text[s..i]));
+        }
+    }
+
+    if let Some(s) = start {
+        words.push((s..text.len(), &text[s..]));
+    }
+
+    words
+}
+
+/// Turns a word into a search term, or returns None for stop words.
+fn term(stemmer: &Stemmer, word: &str) -> Option<String> {
+    let word = word.to_lowercase();
+
+    if STOP_WORDS.contains(&word.as_str()) {
+        None
+    } else {
+        Some(stemmer.stem(&word).into_owned())
+    }
+}
+
+/// Splits text into lowercase, stemmed search terms, leaving out stop words.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let stemmer = Stemmer::create(Algorithm::English);
+
+    words(text)
+        .into_iter()
+        .filter_map(|(_, word)| term(&stemmer, word))
+        .collect()
+}
+
+/// A note field that can be searched.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SearchField {
+    Title,
+    Tags,
+    Desc,
+    Content,
+}
+
+impl SearchField {
+    pub const ALL: [SearchField; 4] = [
+        SearchField::Title,
+        SearchField::Tags,
+        SearchField::Desc,
+        SearchField::Content,
+    ];
+
+    /// Matches in fields with a higher weight rank a note higher.
+    pub fn weight(self) -> usize {
+        match self {
+            SearchField::Title | SearchField::Tags => 3,
+            SearchField::Desc => 2,
+            SearchField::Content => 1,
+        }
+    }
+
+    /// Returns the text of this field of a note. Tags are separated by
+    /// spaces rather than commas, so byte ranges line up with how they are
+    /// displayed.
+    pub fn text(self, note: &Note) -> String {
+        match self {
+            SearchField::Title => note.title().to_string(),
+            SearchField::Tags => note.tags.to_string().replace(',', " "),
+            SearchField::Desc => note.desc.clone(),
+            SearchField::Content => note.content.clone(),
+        }
+    }
+
+    /// Returns the search terms in this field of a note.
+    pub fn tokens(self, note: &Note) -> Vec<String> {
+        tokenize(&self.text(note))
+    }
+}
+
+impl FromStr for SearchField {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "title" => Ok(SearchField::Title),
+            "tags" => Ok(SearchField::Tags),
+            "desc" => Ok(SearchField::Desc),
+            "content" => Ok(SearchField::Content),
+            _ => Err(Error::new(NotesError::InvalidSearchField(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for SearchField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// The byte ranges of matches in each field of a note.
+pub type FieldSpans = Vec<(SearchField, Range<usize>)>;
+
+/// How the words of a query are matched against note fields.
+#[derive(Debug, Clone)]
+pub enum SearchMode {
+    /// Stemmed words, so `deploying` matches `deploy`.
+    Terms,
+    /// A case-insensitive regular expression.
+    Regex(Regex),
+    /// Words within a small number of typos of the query words.
+    Fuzzy,
+}
+
+/// A case-insensitive search for notes matching every one of a list of
+/// terms in any of the given fields, optionally within a range of days.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub terms: Vec<String>,
+    pub fields: Vec<SearchField>,
+    pub mode: SearchMode,
+    pub time: TimeFilter,
+}
+
+impl Search {
+    /// Returns a search for the stemmed words of the query in all fields.
+    pub fn new(query: &str) -> Self {
+        Search {
+            terms: tokenize(query),
+            fields: SearchField::ALL.to_vec(),
+            mode: SearchMode::Terms,
+            time: TimeFilter::default(),
+        }
+    }
+
+    /// Returns a search for a regular expression in all fields.
+    pub fn regex(query: &str) -> anyhow::Result<Self> {
+        let regex = RegexBuilder::new(query).case_insensitive(true).build()?;
+
+        Ok(Search {
+            terms: vec![query.to_string()],
+            fields: SearchField::ALL.to_vec(),
+            mode: SearchMode::Regex(regex),
+            time: TimeFilter::default(),
+        })
+    }
+
+    /// Returns a typo-tolerant search for the words of the query in all
+    /// fields.
+    pub fn fuzzy(query: &str) -> Self {
+        Search {
+            terms: words(query)
+                .into_iter()
+                .map(|(_, w)| w.to_lowercase())
+                .collect(),
+            fields: SearchField::ALL.to_vec(),
+            mode: SearchMode::Fuzzy,
+            time: TimeFilter::default(),
+        }
+    }
+
+    pub fn with_fields(mut self, fields: Vec<SearchField>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    pub fn with_time(mut self, time: TimeFilter) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Checks whether the search can be answered by the search index.
+    pub fn is_indexable(&self) -> bool {
+        matches!(self.mode, SearchMode::Terms)
+    }
+
+    /// Scores how well a note matches the search. Returns None if a term is
+    /// missing from all of the searched fields, or if there are no terms.
+    pub fn score(&self, note: &Note) -> Option<usize> {
+        self.matches(note).map(|(score, _)| score)
+    }
+
+    /// Scores how well a note matches the search and returns the byte ranges
+    /// of each match in each field. Returns None if a term is missing from all
+    /// of the searched fields, if there are no terms, or if the note is outside
+    /// of the time filter.
+    pub fn matches(&self, note: &Note) -> Option<(usize, FieldSpans)> {
+        if self.terms.is_empty() || !self.time.matches(note) {
+            return None;
+        }
+
+        let mut term_scores = vec![0; self.terms.len()];
+        let mut spans = vec![];
+
+        for field in self.fields.iter() {
+            for (term, span, score) in self.find(&field.text(note)) {
+                term_scores[term] += score * field.weight();
+                spans.push((*field, span));
+            }
+        }
+
+        if term_scores.contains(&0) {
+            return None;
+        }
+
+        Some((term_scores.iter().sum(), spans))
+    }
+
+    /// Finds each match in some text. Returns the index of the term that
+    /// matched, the byte range of the match, and how strong the match is.
+    fn find(&self, text: &str) -> Vec<(usize, Range<usize>, usize)> {
+        match &self.mode {
+            SearchMode::Terms => {
+                let stemmer = Stemmer::create(Algorithm::English);
+
+                words(text)
+                    .into_iter()
+                    .filter_map(|(span, word)| {
+                        let word = term(&stemmer, word)?;
+                        let i = self.terms.iter().position(|t| *t == word)?;
+
+                        Some((i, span, 1))
+                    })
+                    .collect()
+            }
+            SearchMode::Regex(regex) => regex
+                .find_iter(text)
+                .filter(|m| !m.as_str().is_empty())
+                .map(|m| (0, m.range(), 1))
+                .collect(),
+            SearchMode::Fuzzy => words(text)
+                .into_iter()
+                .filter_map(|(span, word)| {
+                    let word = word.to_lowercase();
+
+                    self.terms
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, t)| fuzzy_score(t, &word).map(|score| (i, score)))
+                        .max_by_key(|(_, score)| *score)
+                        .map(|(i, score)| (i, span, score))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Scores how closely a word matches a query word, allowing one typo in words
+/// of four to six characters and two in longer words. Exact matches score 100.
+fn fuzzy_score(query: &str, word: &str) -> Option<usize> {
+    let len = query.chars().count().max(word.chars().count());
+    let allowed = match query.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+
+    let distance = damerau_levenshtein(query, word);
+
+    if distance > allowed {
+        return None;
+    }
+
+    Some((len - distance) * 100 / len)
+}
+
+/// A note that matched a search, along with where it matched. Its accessors
+/// mirror `NoteWithId`, but highlight the matches.
+pub struct SearchMatch<'a> {
+    pub note: NoteWithId<'a>,
+    pub score: usize,
+    pub spans: FieldSpans,
+}
+
+impl<'a> SearchMatch<'a> {
+    fn spans(&self, field: SearchField) -> Vec<Range<usize>> {
+        self.spans
+            .iter()
+            .filter(|(f, _)| *f == field)
+            .map(|(_, span)| span.clone())
+            .collect()
+    }
+
+    pub fn id(&self) -> usize {
+        self.note.id()
+    }
+
+    pub fn created(&self) -> &String {
+        self.note.created()
+    }
+
+    pub fn modified(&self) -> &String {
+        self.note.modified()
+    }
+
+    pub fn tags(&self) -> Highlighted<Tags> {
+        Highlighted::new(self.note.tags().clone(), self.spans(SearchField::Tags))
+    }
+
+    pub fn title(&self) -> Highlighted<String> {
+        Highlighted::new(self.note.title(), self.spans(SearchField::Title))
+    }
+
+    pub fn content(&self) -> Highlighted<String> {
+        Highlighted::new(
+            self.note.content().clone(),
+            self.spans(SearchField::Content),
+        )
+    }
+
+    pub fn desc(&self) -> Highlighted<String> {
+        Highlighted::new(self.note.desc().clone(), self.spans(SearchField::Desc))
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+}
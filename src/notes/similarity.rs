@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::tags::*;
+
+use super::search::tokenize;
+use super::{Note, NoteWithId};
+
+/// A term's weight in each note, normalized so that the dot product of two
+/// vectors is their cosine similarity.
+type Vector = BTreeMap<String, f64>;
+
+/// TF-IDF vectors of every note, built over their content, description and
+/// tags. Terms that few notes share weigh more than common ones.
+pub struct TfIdf {
+    vectors: Vec<Vector>,
+}
+
+impl TfIdf {
+    pub fn build(notes: &[Note]) -> Self {
+        let counts: Vec<BTreeMap<String, usize>> = notes.iter().map(term_counts).collect();
+
+        let mut doc_freq: BTreeMap<&str, usize> = BTreeMap::new();
+        for terms in counts.iter() {
+            for term in terms.keys() {
+                *doc_freq.entry(term).or_insert(0) += 1;
+            }
+        }
+
+        let len = notes.len() as f64;
+        let vectors = counts
+            .iter()
+            .map(|terms| {
+                let mut vector: Vector = terms
+                    .iter()
+                    .map(|(term, count)| {
+                        let df = doc_freq[term.as_str()] as f64;
+                        let idf = ((1.0 + len) / (1.0 + df)).ln() + 1.0;
+                        (term.clone(), (1.0 + (*count as f64).ln()) * idf)
+                    })
+                    .collect();
+
+                let norm = vector.values().map(|w| w * w).sum::<f64>().sqrt();
+                if norm > 0.0 {
+                    vector.values_mut().for_each(|w| *w /= norm);
+                }
+
+                vector
+            })
+            .collect();
+
+        TfIdf { vectors }
+    }
+
+    /// Returns the cosine similarity of two notes, from 0 to 1.
+    pub fn similarity(&self, a: usize, b: usize) -> f64 {
+        let (a, b) = (&self.vectors[a], &self.vectors[b]);
+
+        a.iter()
+            .filter_map(|(term, weight)| b.get(term).map(|other| weight * other))
+            .sum()
+    }
+
+    /// Returns every other note that shares a term with the given note, most
+    /// similar first.
+    pub fn related(&self, id: usize) -> Vec<(f64, usize)> {
+        let mut related: Vec<(f64, usize)> = (0..self.vectors.len())
+            .filter(|other| *other != id)
+            .map(|other| (self.similarity(id, other), other))
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+
+        related.sort_by(|(a, a_id), (b, b_id)| b.total_cmp(a).then(a_id.cmp(b_id)));
+
+        related
+    }
+}
+
+/// Counts the search terms in a note's content, description and tags.
+fn term_counts(note: &Note) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+
+    let tags: Vec<String> = note.tags.0.iter().map(String::from).collect();
+    let text = [note.content.as_str(), note.desc.as_str(), &tags.join(" ")].join(" ");
+
+    for term in tokenize(&text) {
+        *counts.entry(term).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/// The number of consecutive words in a shingle.
+const SHINGLE_LEN: usize = 3;
+
+/// Returns the runs of consecutive lowercase words in some text, ignoring
+/// punctuation. Texts shorter than a shingle are a single shingle, and texts
+/// without words have none.
+fn shingles(text: &str) -> BTreeSet<Vec<String>> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    if words.is_empty() {
+        return BTreeSet::new();
+    }
+
+    if words.len() <= SHINGLE_LEN {
+        return std::iter::once(words).collect();
+    }
+
+    words.windows(SHINGLE_LEN).map(|w| w.to_vec()).collect()
+}
+
+/// Returns the Jaccard similarity of the shingles of two notes' content, from
+/// 0 to 1. Exact duplicates have a similarity of 1, unless they have no words.
+pub fn shingle_similarity(a: &Note, b: &Note) -> f64 {
+    jaccard(&shingles(&a.content), &shingles(&b.content))
+}
+
+/// Returns the Jaccard similarity of two sets of shingles, or 0 if both are
+/// empty.
+fn jaccard(a: &BTreeSet<Vec<String>>, b: &BTreeSet<Vec<String>>) -> f64 {
+    let union = a.union(b).count();
+
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Groups notes whose content is at least `threshold` similar by shingles,
+/// directly or through other notes. Returns each group of two or more notes,
+/// ordered by their lowest id. Notes without words are never duplicates.
+pub fn duplicate_clusters(notes: &[Note], threshold: f64) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..notes.len()).collect();
+    let sets: Vec<BTreeSet<Vec<String>>> = notes.iter().map(|n| shingles(&n.content)).collect();
+
+    fn root(parents: &mut [usize], mut id: usize) -> usize {
+        while parents[id] != id {
+            parents[id] = parents[parents[id]];
+            id = parents[id];
+        }
+        id
+    }
+
+    for a in (0..notes.len()).filter(|a| !sets[*a].is_empty()) {
+        for b in (a + 1..notes.len()).filter(|b| !sets[*b].is_empty()) {
+            if jaccard(&sets[a], &sets[b]) >= threshold {
+                let (ra, rb) = (root(&mut parents, a), root(&mut parents, b));
+                parents[ra.max(rb)] = ra.min(rb);
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for id in 0..notes.len() {
+        let r = root(&mut parents, id);
+        clusters.entry(r).or_default().push(id);
+    }
+
+    clusters.into_values().filter(|c| c.len() > 1).collect()
+}
+
+/// A note along with how similar it is to another note.
+pub struct RelatedNote<'a> {
+    pub note: NoteWithId<'a>,
+    pub score: f64,
+}
+
+impl<'a> RelatedNote<'a> {
+    pub fn id(&self) -> usize {
+        self.note.id()
+    }
+
+    pub fn created(&self) -> &String {
+        self.note.created()
+    }
+
+    pub fn tags(&self) -> &Tags {
+        self.note.tags()
+    }
+
+    pub fn content(&self) -> &String {
+        self.note.content()
+    }
+
+    pub fn desc(&self) -> &String {
+        self.note.desc()
+    }
+
+    /// The cosine similarity, to two decimal places.
+    pub fn similarity(&self) -> String {
+        format!("{:.2}", self.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+
+    #[test]
+    fn rank_related_notes() {
+        let notes = [
+            NoteBuilder::new()
+                .with_content("Deploy the staging server")
+                .with_tags(Tags::from(vec!["ops"]))
+                .build(),
+            NoteBuilder::new().with_content("Lunch with sam").build(),
+            NoteBuilder::new()
+                .with_content("The staging deploy failed")
+                .with_tags(Tags::from(vec!["ops"]))
+                .build(),
+            NoteBuilder::new()
+                .with_content("Server maintenance")
+                .build(),
+        ];
+        let tfidf = TfIdf::build(&notes);
+
+        let related: Vec<usize> = tfidf.related(0).into_iter().map(|(_, id)| id).collect();
+        assert_eq!(related, vec![2, 3]);
+
+        assert!((tfidf.similarity(0, 0) - 1.0).abs() < 1e-9);
+        assert_eq!(tfidf.similarity(0, 1), 0.0);
+    }
+
+    #[test]
+    fn cluster_duplicates() {
+        let notes: Vec<Note> = [
+            "Call the bank about the mortgage rate",
+            "Lunch with sam",
+            "call the bank about the mortgage rate!",
+            "Call the bank about the mortgage rate tomorrow",
+            "lunch with Sam",
+            "Deploy the staging server",
+            "",
+            "--",
+        ]
+        .iter()
+        .map(|content| NoteBuilder::new().with_content(content).build())
+        .collect();
+
+        assert_eq!(shingle_similarity(&notes[0], &notes[2]), 1.0);
+        assert_eq!(shingle_similarity(&notes[6], &notes[7]), 0.0);
+        assert_eq!(
+            duplicate_clusters(&notes, 0.7),
+            vec![vec![0, 2, 3], vec![1, 4]]
+        );
+        assert_eq!(
+            duplicate_clusters(&notes, 1.0),
+            vec![vec![0, 2], vec![1, 4]]
+        );
+    }
+}
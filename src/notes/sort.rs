@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Error;
+
+use crate::errors::NotesError;
+
+use super::{NoteWithId, RelatedNote, SearchMatch};
+
+/// A listed item that stands for a note, such as a search match.
+pub trait AsNoteWithId<'a> {
+    fn as_note_with_id(&self) -> NoteWithId<'a>;
+}
+
+impl<'a> AsNoteWithId<'a> for NoteWithId<'a> {
+    fn as_note_with_id(&self) -> NoteWithId<'a> {
+        *self
+    }
+}
+
+impl<'a> AsNoteWithId<'a> for SearchMatch<'a> {
+    fn as_note_with_id(&self) -> NoteWithId<'a> {
+        self.note
+    }
+}
+
+impl<'a> AsNoteWithId<'a> for RelatedNote<'a> {
+    fn as_note_with_id(&self) -> NoteWithId<'a> {
+        self.note
+    }
+}
+
+/// What notes can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Created,
+    Modified,
+    Id,
+    Tag,
+    Length,
+}
+
+impl SortKey {
+    /// Compares two notes. Notes without tags sort after tagged ones, and
+    /// length is the number of characters of content.
+    pub fn compare(self, a: &NoteWithId<'_>, b: &NoteWithId<'_>) -> Ordering {
+        match self {
+            SortKey::Created => a.created().cmp(b.created()),
+            SortKey::Modified => a.modified().cmp(b.modified()),
+            SortKey::Id => a.id().cmp(&b.id()),
+            SortKey::Tag => {
+                let first_tag = |note: &NoteWithId<'_>| {
+                    let first = note.tags().0.first().map(String::from);
+                    (first.is_none(), first)
+                };
+                first_tag(a).cmp(&first_tag(b))
+            }
+            SortKey::Length => a
+                .content()
+                .chars()
+                .count()
+                .cmp(&b.content().chars().count()),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "created" => Ok(SortKey::Created),
+            "modified" => Ok(SortKey::Modified),
+            "id" => Ok(SortKey::Id),
+            "tag" => Ok(SortKey::Tag),
+            "length" => Ok(SortKey::Length),
+            _ => Err(Error::new(NotesError::InvalidSortKey(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// How a list of notes is ordered and paged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Listing {
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl Listing {
+    /// Orders and pages the items. Items are only collected when they need to
+    /// be sorted or reversed; otherwise they are paged as they are produced.
+    /// Sorting is stable, so ties keep their original order.
+    pub fn apply<'a, T, I>(&self, items: I) -> Box<dyn Iterator<Item = T> + 'a>
+    where
+        T: AsNoteWithId<'a> + 'a,
+        I: Iterator<Item = T> + 'a,
+    {
+        let items: Box<dyn Iterator<Item = T> + 'a> = if self.sort.is_none() && !self.reverse {
+            Box::new(items)
+        } else {
+            let mut items: Vec<T> = items.collect();
+
+            if let Some(key) = self.sort {
+                items.sort_by(|a, b| key.compare(&a.as_note_with_id(), &b.as_note_with_id()));
+            }
+
+            if self.reverse {
+                items.reverse();
+            }
+
+            Box::new(items.into_iter())
+        };
+
+        Box::new(
+            items
+                .skip(self.offset)
+                .take(self.limit.unwrap_or(usize::MAX)),
+        )
+    }
+
+    /// Orders and pages the items, unless none are left, in which case it
+    /// returns None.
+    pub fn collect<'a, T, I>(&self, items: I) -> Option<Vec<T>>
+    where
+        T: AsNoteWithId<'a> + 'a,
+        I: Iterator<Item = T> + 'a,
+    {
+        let items: Vec<T> = self.apply(items).collect();
+
+        if items.is_empty() {
+            None
+        } else {
+            Some(items)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::tags::Tags;
+
+    #[test]
+    fn sort_and_page() {
+        let notes = [
+            NoteBuilder::new()
+                .with_content("medium")
+                .with_tags(Tags::from(vec!["b"]))
+                .build(),
+            NoteBuilder::new().with_content("a longer note").build(),
+            NoteBuilder::new()
+                .with_content("short")
+                .with_tags(Tags::from(vec!["a"]))
+                .build(),
+        ];
+        let ids = |listing: Listing| -> Vec<usize> {
+            listing
+                .apply(notes.iter().enumerate().map(|(i, n)| NoteWithId(i, n)))
+                .map(|n| n.id())
+                .collect()
+        };
+
+        assert_eq!(ids(Listing::default()), vec![0, 1, 2]);
+        assert_eq!(
+            ids(Listing {
+                sort: Some(SortKey::Length),
+                ..Listing::default()
+            }),
+            vec![2, 0, 1]
+        );
+        assert_eq!(
+            ids(Listing {
+                sort: Some(SortKey::Tag),
+                reverse: true,
+                ..Listing::default()
+            }),
+            vec![1, 0, 2]
+        );
+        assert_eq!(
+            ids(Listing {
+                limit: Some(1),
+                offset: 1,
+                ..Listing::default()
+            }),
+            vec![1]
+        );
+    }
+}
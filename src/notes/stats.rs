@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+/// A summary of the notes, printed by `notes stats`.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct NoteStats {
+    pub notes: usize,
+    pub untagged: usize,
+    /// The number of notes with each tag, most used first.
+    pub tags: Vec<TagCount>,
+    /// The average number of characters of content.
+    pub average_length: f64,
+    pub longest: Option<LongestNote>,
+    /// Notes created per week since the first note was created.
+    pub notes_per_week: f64,
+    /// The size in bytes of the notes file and the files stored next to it.
+    pub store_size: u64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub notes: usize,
+}
+
+impl TagCount {
+    pub fn tag(&self) -> &String {
+        &self.tag
+    }
+
+    pub fn notes(&self) -> usize {
+        self.notes
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LongestNote {
+    pub id: usize,
+    pub length: usize,
+}
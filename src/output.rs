@@ -0,0 +1,292 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use anyhow::Error;
+use serde::Serialize;
+
+use crate::errors::NotesError;
+use crate::notes::NoteWithId;
+
+/// How notes are printed: as a table for people, or in a format for scripts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+/// The formats for scripts, which every output but a table is in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    pub fn is_table(self) -> bool {
+        self == OutputFormat::Table
+    }
+
+    /// Returns the format for scripts, or None for a table.
+    pub fn data(self) -> Option<DataFormat> {
+        match self {
+            OutputFormat::Table => None,
+            OutputFormat::Json => Some(DataFormat::Json),
+            OutputFormat::Ndjson => Some(DataFormat::Ndjson),
+            OutputFormat::Csv => Some(DataFormat::Csv),
+            OutputFormat::Tsv => Some(DataFormat::Tsv),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(Error::new(NotesError::InvalidOutputFormat(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// A note as it is written in machine-readable output. The field names and
+/// their order are stable, so scripts can rely on them.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NoteRecord<'a> {
+    pub id: usize,
+    pub created: &'a str,
+    pub modified: &'a str,
+    pub tags: Vec<String>,
+    pub content: &'a str,
+    pub desc: &'a str,
+    pub contexts: &'a [String],
+    pub projects: &'a [String],
+}
+
+impl<'a> NoteRecord<'a> {
+    const FIELDS: [&'static str; 8] = [
+        "id", "created", "modified", "tags", "content", "desc", "contexts", "projects",
+    ];
+
+    /// The fields as text for CSV and TSV, with lists joined by commas.
+    fn columns(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.created.to_string(),
+            self.modified.to_string(),
+            self.tags.join(","),
+            self.content.to_string(),
+            self.desc.to_string(),
+            self.contexts.join(","),
+            self.projects.join(","),
+        ]
+    }
+}
+
+impl<'a> From<NoteWithId<'a>> for NoteRecord<'a> {
+    fn from(note: NoteWithId<'a>) -> Self {
+        NoteRecord {
+            id: note.id(),
+            created: note.1.created.as_str(),
+            modified: note.1.modified.as_str(),
+            tags: Vec::from(&note.1.tags),
+            content: note.1.content.as_str(),
+            desc: note.1.desc.as_str(),
+            contexts: &note.1.contexts,
+            projects: &note.1.projects,
+        }
+    }
+}
+
+/// Writes notes to stdout in a machine-readable format. No notes is an empty
+/// JSON array, no lines of NDJSON, or just the header row of CSV and TSV.
+pub fn print_notes<'a, I>(format: DataFormat, notes: I) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = NoteWithId<'a>>,
+{
+    let records = notes.into_iter().map(NoteRecord::from);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match format {
+        DataFormat::Json => {
+            let records: Vec<NoteRecord<'_>> = records.collect();
+            serde_json::to_writer_pretty(&mut out, &records)?;
+            writeln!(out)?;
+        }
+        DataFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut out, &record)?;
+                writeln!(out)?;
+            }
+        }
+        DataFormat::Csv | DataFormat::Tsv => {
+            let rows = records.map(|record| record.columns());
+            write_rows(format, &mut out, &NoteRecord::FIELDS, rows)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a single value, such as a summary, as pretty JSON or as one line
+/// of NDJSON. `what` names the value in the error for other formats.
+pub fn print_value<T: Serialize>(
+    format: OutputFormat,
+    what: &str,
+    value: &T,
+) -> anyhow::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match format {
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut out, value)?,
+        OutputFormat::Ndjson => serde_json::to_writer(&mut out, value)?,
+        _ => {
+            return Err(Error::new(NotesError::UnsupportedOutputFormat(
+                what.to_string(),
+                format,
+            )))
+        }
+    }
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Writes an error to stderr. In machine-readable formats it is an object or
+/// row with a single `error` field holding the message and its causes.
+pub fn print_error(format: OutputFormat, error: &Error) {
+    let message = format!("{:#}", error);
+
+    let result = match format.data() {
+        None => {
+            eprintln!("Error: {:?}", error);
+            Ok(())
+        }
+        Some(DataFormat::Json) | Some(DataFormat::Ndjson) => {
+            let error = serde_json::json!({ "error": message });
+            writeln!(io::stderr(), "{}", error).map_err(Error::from)
+        }
+        Some(format) => write_rows(
+            format,
+            &mut io::stderr(),
+            &["error"],
+            std::iter::once(vec![message]),
+        ),
+    };
+
+    if result.is_err() {
+        eprintln!("Error: {:?}", error);
+    }
+}
+
+/// Writes a header and rows as CSV, or as TSV, where tabs, newlines and
+/// backslashes in fields are escaped as `\t`, `\n` and `\\`.
+fn write_rows<W, I>(format: DataFormat, out: W, header: &[&str], rows: I) -> anyhow::Result<()>
+where
+    W: Write,
+    I: Iterator<Item = Vec<String>>,
+{
+    let mut writer = match format {
+        DataFormat::Tsv => csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(csv::QuoteStyle::Never)
+            .from_writer(out),
+        _ => csv::Writer::from_writer(out),
+    };
+
+    writer.write_record(header)?;
+
+    for row in rows {
+        if format == DataFormat::Tsv {
+            writer.write_record(row.iter().map(|field| escape_tsv(field)))?;
+        } else {
+            writer.write_record(&row)?;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::tags::Tags;
+
+    #[test]
+    fn write_tsv_and_csv() -> anyhow::Result<()> {
+        let rows = vec![vec![
+            "a\tb".to_string(),
+            "line\nbreak, \"quoted\"".to_string(),
+        ]];
+
+        let mut tsv = vec![];
+        write_rows(
+            DataFormat::Tsv,
+            &mut tsv,
+            &["x", "y"],
+            rows.clone().into_iter(),
+        )?;
+        assert_eq!(
+            String::from_utf8(tsv)?,
+            "x\ty\na\\tb\tline\\nbreak, \"quoted\"\n"
+        );
+
+        let mut csv = vec![];
+        write_rows(DataFormat::Csv, &mut csv, &["x", "y"], rows.into_iter())?;
+        assert_eq!(
+            String::from_utf8(csv)?,
+            "x,y\na\tb,\"line\nbreak, \"\"quoted\"\"\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_record() -> anyhow::Result<()> {
+        let note = NoteBuilder::new()
+            .with_content("Deploy")
+            .with_tags(Tags::from(vec!["ops", "rust"]))
+            .build();
+        let record = NoteRecord::from(NoteWithId(3, &note));
+
+        let json = serde_json::to_value(&record)?;
+        assert_eq!(json["id"], 3);
+        assert_eq!(json["tags"], serde_json::json!(["ops", "rust"]));
+        assert_eq!(json["desc"], "");
+
+        let fields: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(fields.len(), NoteRecord::FIELDS.len());
+
+        Ok(())
+    }
+}
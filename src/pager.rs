@@ -0,0 +1,65 @@
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::settings::Settings;
+
+/// The pager used when neither `NOTES_PAGER` nor `PAGER` is set. `-R` keeps
+/// colors.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Returns the pager command: `NOTES_PAGER`, then the config file, then
+/// `PAGER`, then `less -R`. An empty `NOTES_PAGER` or a pager of `cat` turns
+/// paging off.
+fn pager(settings: &Settings) -> Option<String> {
+    let pager = match env::var("NOTES_PAGER") {
+        Ok(pager) => pager,
+        Err(_) => settings
+            .config
+            .pager
+            .clone()
+            .or_else(|| env::var("PAGER").ok())
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PAGER.to_string()),
+    };
+
+    Some(pager).filter(|pager| !pager.trim().is_empty() && pager.trim() != "cat")
+}
+
+/// Prints text through the pager if paging is on, stdout is a terminal and
+/// the text is taller than it, or straight to stdout otherwise or if the
+/// pager cannot be started.
+pub fn page(text: &str, settings: &Settings) -> anyhow::Result<()> {
+    let tall = console::Term::stdout()
+        .size_checked()
+        .is_some_and(|(rows, _)| text.lines().count() >= rows as usize);
+
+    if tall && settings.paging {
+        if let Some(()) = pager(settings).and_then(|pager| run_pager(&pager, text)) {
+            return Ok(());
+        }
+    }
+
+    println!("{}", text);
+
+    Ok(())
+}
+
+/// Pipes text to the pager and waits for it to exit. Returns `None` if the
+/// pager could not be started.
+fn run_pager(pager: &str, text: &str) -> Option<()> {
+    let mut args = pager.split_whitespace();
+
+    let mut child = Command::new(args.next()?)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its input if it is quit early, which is fine.
+        let _ = writeln!(stdin, "{}", text);
+    }
+
+    child.wait().ok().map(|_| ())
+}
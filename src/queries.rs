@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::NotesError;
+use crate::notes::Query;
+use crate::util::{read_sidecar, write_sidecar};
+
+/// A query expression saved under a name, so it can be run again with
+/// `notes query run <name>` or `notes get @name`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub expr: String,
+}
+
+/// A saved query along with the number of notes it currently matches, or the
+/// reason it no longer parses.
+pub struct SavedQueryCount<'a>(pub &'a SavedQuery, pub Result<usize, String>);
+
+impl<'a> SavedQueryCount<'a> {
+    pub fn name(&self) -> &String {
+        &self.0.name
+    }
+
+    pub fn query(&self) -> &String {
+        &self.0.expr
+    }
+
+    pub fn notes(&self) -> String {
+        match &self.1 {
+            Ok(count) => count.to_string(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+}
+
+/// The saved queries, stored next to the notes file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SavedQueries(Vec<SavedQuery>);
+
+impl SavedQueries {
+    /// Returns the path of the saved queries that belong to the given notes
+    /// file.
+    pub fn path_for<P: AsRef<Path>>(notes_path: P) -> PathBuf {
+        notes_path.as_ref().with_extension("queries")
+    }
+
+    /// Reads the saved queries from the given file, or returns no queries if
+    /// the file does not exist.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        read_sidecar(path, NotesError::QueriesDeserialization)
+    }
+
+    /// Writes the saved queries to the given file. Nothing is written if there
+    /// are no queries unless the file already exists.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        write_sidecar(self, self.0.is_empty(), path)
+    }
+
+    /// Returns all of the saved queries, ordered by name.
+    pub fn get_all(&self) -> &Vec<SavedQuery> {
+        &self.0
+    }
+
+    /// Returns the query saved under the given name. A leading `@` is ignored.
+    pub fn get(&self, name: &str) -> anyhow::Result<&SavedQuery> {
+        let name = name.trim_start_matches('@');
+
+        self.0
+            .iter()
+            .find(|q| q.name == name)
+            .ok_or_else(|| Error::new(NotesError::QueryNotFound(name.to_string())))
+    }
+
+    /// Saves a query under a name, replacing any query already saved under
+    /// it. The expression must parse. Returns true if a query was replaced.
+    pub fn save(&mut self, name: &str, expr: &str) -> anyhow::Result<bool> {
+        let name = name.trim_start_matches('@');
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(Error::new(NotesError::InvalidQueryName(name.to_string())));
+        }
+
+        Query::parse(expr)?;
+
+        let query = SavedQuery {
+            name: name.to_string(),
+            expr: expr.to_string(),
+        };
+
+        match self.0.binary_search_by(|q| q.name.as_str().cmp(name)) {
+            Ok(i) => {
+                self.0[i] = query;
+                Ok(true)
+            }
+            Err(i) => {
+                self.0.insert(i, query);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Deletes the query saved under the given name and returns it.
+    pub fn delete(&mut self, name: &str) -> anyhow::Result<SavedQuery> {
+        let name = name.trim_start_matches('@');
+
+        match self.0.iter().position(|q| q.name == name) {
+            Some(i) => Ok(self.0.remove(i)),
+            None => Err(Error::new(NotesError::QueryNotFound(name.to_string()))),
+        }
+    }
+
+    /// Replaces a leading `@name` in a query expression with the expression
+    /// saved under that name, so saved queries can be narrowed further.
+    pub fn expand(&self, expr: &str) -> anyhow::Result<String> {
+        let expr = expr.trim_start();
+
+        if !expr.starts_with('@') {
+            return Ok(expr.to_string());
+        }
+
+        let end = expr.find(char::is_whitespace).unwrap_or(expr.len());
+        let saved = self.get(&expr[..end])?;
+
+        Ok(format!("{}{}", saved.expr, &expr[end..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_expand() -> anyhow::Result<()> {
+        let mut queries = SavedQueries::default();
+
+        assert!(!queries.save("oncall", "tag:oncall")?);
+        assert!(!queries.save("@done", "tag:done")?);
+        assert!(queries.save("oncall", "tag:oncall created>7d")?);
+        assert!(queries.save("on call", "tag:oncall").is_err());
+        assert!(queries.save("broken", "tga:oncall").is_err());
+
+        let names: Vec<&str> = queries.get_all().iter().map(|q| q.name.as_str()).collect();
+        assert_eq!(names, vec!["done", "oncall"]);
+
+        assert_eq!(
+            queries.expand("@oncall -tag:done")?,
+            "tag:oncall created>7d -tag:done"
+        );
+        assert_eq!(queries.expand("tag:rust")?, "tag:rust");
+        assert!(queries.expand("@missing").is_err());
+
+        queries.delete("done")?;
+        assert!(queries.get("@done").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Error;
+use console::{style, Color};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::NotesError;
+use crate::tags::*;
+use crate::theme::Theme;
+use crate::util::{read_sidecar, write_sidecar};
+
+/// A color that a tag can be displayed in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TagColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl TagColor {
+    fn color(self) -> Color {
+        match self {
+            TagColor::Black => Color::Black,
+            TagColor::Red => Color::Red,
+            TagColor::Green => Color::Green,
+            TagColor::Yellow => Color::Yellow,
+            TagColor::Blue => Color::Blue,
+            TagColor::Magenta => Color::Magenta,
+            TagColor::Cyan => Color::Cyan,
+            TagColor::White => Color::White,
+        }
+    }
+}
+
+impl FromStr for TagColor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "black" => Ok(TagColor::Black),
+            "red" => Ok(TagColor::Red),
+            "green" => Ok(TagColor::Green),
+            "yellow" => Ok(TagColor::Yellow),
+            "blue" => Ok(TagColor::Blue),
+            "magenta" => Ok(TagColor::Magenta),
+            "cyan" => Ok(TagColor::Cyan),
+            "white" => Ok(TagColor::White),
+            _ => Err(Error::new(NotesError::InvalidColor(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for TagColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// A registered tag with its description, display color, and aliases.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagRecord {
+    pub tag: Tag,
+    pub desc: String,
+    pub color: Option<TagColor>,
+    pub aliases: Tags,
+}
+
+impl TagRecord {
+    pub fn new(tag: Tag) -> Self {
+        TagRecord {
+            tag,
+            desc: String::new(),
+            color: None,
+            aliases: Tags(vec![]),
+        }
+    }
+
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    pub fn desc(&self) -> &String {
+        &self.desc
+    }
+
+    pub fn color(&self) -> String {
+        self.color.map(|c| c.to_string()).unwrap_or_default()
+    }
+
+    pub fn aliases(&self) -> &Tags {
+        &self.aliases
+    }
+}
+
+/// The tags that have been registered, stored next to the notes file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TagRegistry(Vec<TagRecord>);
+
+impl TagRegistry {
+    /// Returns the path of the registry that belongs to the given notes file.
+    pub fn path_for<P: AsRef<Path>>(notes_path: P) -> PathBuf {
+        notes_path.as_ref().with_extension("tags")
+    }
+
+    /// Reads the registry from the given file, or returns an empty registry if
+    /// the file does not exist.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        read_sidecar(path, NotesError::RegistryDeserialization)
+    }
+
+    /// Writes the registry to the given file. Nothing is written for an empty
+    /// registry unless the file already exists.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        write_sidecar(self, self.0.is_empty(), path)
+    }
+
+    /// Returns all of the registered tags, unless there are none, in which
+    /// case it returns None.
+    pub fn get_all(&self) -> Option<&[TagRecord]> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(self.0.as_slice())
+        }
+    }
+
+    /// Gets the record for a tag or any of its aliases.
+    pub fn get(&self, tag: &Tag) -> Option<&TagRecord> {
+        self.0
+            .iter()
+            .find(|r| &r.tag == tag || r.aliases.0.contains(tag))
+    }
+
+    /// Returns the record for a tag, registering the tag if it has no
+    /// record yet.
+    pub fn get_or_insert(&mut self, tag: Tag) -> &mut TagRecord {
+        let index = match self.0.iter().position(|r| r.tag == tag) {
+            Some(index) => index,
+            None => {
+                self.0.push(TagRecord::new(tag));
+                self.0.len() - 1
+            }
+        };
+
+        &mut self.0[index]
+    }
+
+    /// Adds an alias to a registered tag. Fails if the alias already belongs
+    /// to another tag.
+    pub fn add_alias(&mut self, tag: Tag, alias: Tag) -> anyhow::Result<()> {
+        if let Some(record) = self.get(&alias) {
+            if record.tag != tag {
+                return Err(Error::new(NotesError::AliasInUse(
+                    alias.to_string(),
+                    record.tag.to_string(),
+                )));
+            }
+        }
+
+        let record = self.get_or_insert(tag);
+        if record.tag != alias && !record.aliases.0.contains(&alias) {
+            record.aliases.push(alias);
+        }
+
+        Ok(())
+    }
+
+    /// Renames a registered tag. If the new name is already registered, the
+    /// old record is merged into it: its aliases are added, and its
+    /// description and color fill in any that the new record lacks.
+    pub fn rename(&mut self, old: &Tag, new: Tag) {
+        let index = match self.0.iter().position(|r| &r.tag == old) {
+            Some(index) => index,
+            None => return,
+        };
+
+        if self.0.iter().all(|r| r.tag != new) {
+            self.0[index].tag = new;
+            return;
+        }
+
+        let old_record = self.0.remove(index);
+
+        let record = self.get_or_insert(new);
+        for alias in old_record.aliases.0 {
+            if alias != record.tag && !record.aliases.0.contains(&alias) {
+                record.aliases.push(alias);
+            }
+        }
+        if record.desc.is_empty() {
+            record.desc = old_record.desc;
+        }
+        if record.color.is_none() {
+            record.color = old_record.color;
+        }
+    }
+
+    /// Removes a tag's record, returning it if it was registered.
+    pub fn remove(&mut self, tag: &Tag) -> Option<TagRecord> {
+        let index = self.0.iter().position(|r| &r.tag == tag)?;
+
+        Some(self.0.remove(index))
+    }
+
+    /// Resolves an alias to the tag it stands for. Tags that are not an alias
+    /// resolve to themselves.
+    pub fn resolve<'a>(&'a self, tag: &'a Tag) -> &'a Tag {
+        self.get(tag).map(|r| &r.tag).unwrap_or(tag)
+    }
+
+    /// Renders a tag in its registered color, or in the theme's tag style if
+    /// it has none.
+    pub fn paint(&self, tag: &Tag, theme: &Theme) -> String {
+        match self.get(tag).and_then(|r| r.color) {
+            Some(color) => style(tag).fg(color.color()).to_string(),
+            None => theme.tag.apply_to(tag).to_string(),
+        }
+    }
+}
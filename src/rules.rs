@@ -0,0 +1,192 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::NotesError;
+use crate::tags::*;
+use crate::util::{read_sidecar, write_sidecar};
+
+/// A regular expression that is compiled once, when the rule is created or
+/// read, and stored as its source.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct ContentPattern(Regex);
+
+impl TryFrom<String> for ContentPattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Ok(ContentPattern(Regex::new(&pattern)?))
+    }
+}
+
+impl From<ContentPattern> for String {
+    fn from(pattern: ContentPattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+impl PartialEq for ContentPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+/// What an auto-tagging rule matches against.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RulePattern {
+    /// A regular expression matched against the note content.
+    Content(ContentPattern),
+    /// The extension of the file that the note was created from.
+    FileExtension(String),
+}
+
+impl RulePattern {
+    /// Returns a content pattern, or an error if the pattern is not a valid
+    /// regular expression.
+    pub fn content(pattern: &str) -> anyhow::Result<Self> {
+        Ok(RulePattern::Content(ContentPattern(Regex::new(pattern)?)))
+    }
+
+    /// Checks whether the pattern matches a note with the given content,
+    /// created from the given file.
+    fn matches(&self, content: &str, file: Option<&Path>) -> bool {
+        match self {
+            RulePattern::Content(pattern) => pattern.0.is_match(content),
+            RulePattern::FileExtension(ext) => file
+                .and_then(|f| f.extension())
+                .map(|e| e.to_string_lossy() == ext.trim_start_matches('.'))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl fmt::Display for RulePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulePattern::Content(pattern) => write!(f, "content /{}/", pattern.0),
+            RulePattern::FileExtension(ext) => {
+                write!(f, "file .{}", ext.trim_start_matches('.'))
+            }
+        }
+    }
+}
+
+/// A rule that adds a tag to notes matching a pattern.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagRule {
+    pub pattern: RulePattern,
+    pub tag: Tag,
+}
+
+/// A rule along with its position in the rule list.
+pub struct TagRuleWithId<'a>(pub usize, pub &'a TagRule);
+
+impl<'a> TagRuleWithId<'a> {
+    pub fn id(&self) -> usize {
+        self.0
+    }
+
+    pub fn pattern(&self) -> String {
+        self.1.pattern.to_string()
+    }
+
+    pub fn tag(&self) -> &Tag {
+        &self.1.tag
+    }
+}
+
+/// The tags that the auto-tagging rules add to a note.
+pub struct TagChange {
+    pub id: usize,
+    pub content: String,
+    pub added: Tags,
+}
+
+impl TagChange {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn content(&self) -> &String {
+        &self.content
+    }
+
+    pub fn added(&self) -> &Tags {
+        &self.added
+    }
+}
+
+/// The auto-tagging rules, stored next to the notes file.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TagRules(Vec<TagRule>);
+
+impl TagRules {
+    /// Returns the path of the rules that belong to the given notes file.
+    pub fn path_for<P: AsRef<Path>>(notes_path: P) -> PathBuf {
+        notes_path.as_ref().with_extension("rules")
+    }
+
+    /// Reads the rules from the given file, or returns no rules if the file
+    /// does not exist.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        read_sidecar(path, NotesError::RulesDeserialization)
+    }
+
+    /// Writes the rules to the given file. Nothing is written if there are no
+    /// rules unless the file already exists.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        write_sidecar(self, self.0.is_empty(), path)
+    }
+
+    /// Returns all of the rules and their IDs, unless there are none, in which
+    /// case it returns None.
+    pub fn get_all_with_id(&self) -> Option<Vec<TagRuleWithId<'_>>> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.0
+                .iter()
+                .enumerate()
+                .map(|(i, rule)| TagRuleWithId(i, rule))
+                .collect(),
+        )
+    }
+
+    /// Adds a rule and returns its ID.
+    pub fn push(&mut self, rule: TagRule) -> usize {
+        self.0.push(rule);
+
+        self.0.len() - 1
+    }
+
+    /// Returns an error if index is out of range; otherwise, returns the
+    /// deleted rule.
+    pub fn delete(&mut self, index: usize) -> anyhow::Result<TagRule> {
+        if index >= self.0.len() {
+            Err(Error::new(NotesError::InvalidRuleId(index)))
+        } else {
+            Ok(self.0.remove(index))
+        }
+    }
+
+    /// Returns the tags of every rule that matches a note with the given
+    /// content, created from the given file.
+    pub fn tags_for(&self, content: &str, file: Option<&Path>) -> Tags {
+        let mut tags = Tags(vec![]);
+
+        for rule in self.0.iter() {
+            if rule.pattern.matches(content, file) && !tags.0.contains(&rule.tag) {
+                tags.push(rule.tag.clone());
+            }
+        }
+
+        tags
+    }
+}
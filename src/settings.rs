@@ -0,0 +1,34 @@
+use chrono::Local;
+
+use crate::config::Config;
+use crate::dates::TimeFormat;
+use crate::theme::Theme;
+
+/// The settings of a run: the arguments, falling back to the config file.
+/// `run_app` resolves them once and passes them to whatever prints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub config: Config,
+    pub theme: Theme,
+    pub time_format: TimeFormat,
+    /// Whether output taller than the terminal goes through the pager.
+    pub paging: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            config: Config::default(),
+            theme: Theme::default(),
+            time_format: TimeFormat::default(),
+            paging: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Renders a stored timestamp in the time format.
+    pub fn display_time(&self, stored: &str) -> String {
+        self.time_format.render(stored, Local::now())
+    }
+}
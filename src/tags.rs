@@ -2,8 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt;
 
+use crate::registry::TagRegistry;
+
 /// A list of tags to be attached to a note.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct Tags(pub Vec<Tag>);
 
 impl Tags {
@@ -15,19 +17,40 @@ impl Tags {
         &self.0
     }
 
-    pub fn has_tag(&self, tag: &Tag) -> bool {
-        self.0.contains(tag)
+    /// Checks for a tag, treating registered aliases as the tag they stand
+    /// for.
+    pub fn has_tag(&self, tag: &Tag, registry: &TagRegistry) -> bool {
+        let tag = registry.resolve(tag);
+
+        self.0.iter().any(|t| registry.resolve(t) == tag)
     }
 
-    pub fn contains(&self, other: &Tags) -> bool {
+    pub fn contains(&self, other: &Tags, registry: &TagRegistry) -> bool {
         for tag in other.tags() {
-            if !self.0.contains(tag) {
+            if !self.has_tag(tag, registry) {
                 return false;
             }
         }
 
         true
     }
+
+    /// Replaces every tag in `from` with `into`, without duplicating `into` if
+    /// it is already present. Returns whether any tag was replaced.
+    pub fn replace(&mut self, from: &Tags, into: &Tag) -> bool {
+        let len = self.0.len();
+        self.0.retain(|tag| !from.0.contains(tag));
+
+        if self.0.len() == len {
+            return false;
+        }
+
+        if !self.0.contains(into) {
+            self.push(into.clone());
+        }
+
+        true
+    }
 }
 
 impl fmt::Display for Tags {
@@ -36,11 +59,11 @@ impl fmt::Display for Tags {
     }
 }
 
-impl Into<Vec<String>> for &Tags {
-    fn into(self) -> Vec<String> {
+impl From<&Tags> for Vec<String> {
+    fn from(tags: &Tags) -> Vec<String> {
         let mut strings = vec![];
 
-        for tag in self.tags() {
+        for tag in tags.tags() {
             strings.push(tag.into());
         }
 
@@ -73,7 +96,7 @@ impl From<Vec<&str>> for Tags {
 }
 
 /// A tag that describes a note.
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Tag {
     name: String,
 }
@@ -84,15 +107,15 @@ impl Tag {
     }
 }
 
-impl Into<String> for Tag {
-    fn into(self) -> String {
-        self.name
+impl From<Tag> for String {
+    fn from(tag: Tag) -> String {
+        tag.name
     }
 }
 
-impl Into<String> for &Tag {
-    fn into(self) -> String {
-        self.name.clone()
+impl From<&Tag> for String {
+    fn from(tag: &Tag) -> String {
+        tag.name.clone()
     }
 }
 
@@ -102,8 +125,42 @@ impl From<String> for Tag {
     }
 }
 
+impl From<&str> for Tag {
+    fn from(name: &str) -> Tag {
+        Tag {
+            name: name.to_string(),
+        }
+    }
+}
+
 impl fmt::Display for Tag {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.name)
     }
 }
+
+/// Usage statistics for a single tag across all notes.
+pub struct TagUsage {
+    pub tag: Tag,
+    pub count: usize,
+    pub last_used: String,
+    pub desc: String,
+}
+
+impl TagUsage {
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    pub fn notes(&self) -> usize {
+        self.count
+    }
+
+    pub fn last_used(&self) -> &String {
+        &self.last_used
+    }
+
+    pub fn desc(&self) -> &String {
+        &self.desc
+    }
+}
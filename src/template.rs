@@ -0,0 +1,468 @@
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{Context, Error};
+
+use crate::columns::Column;
+use crate::dates::TimeFormat;
+use crate::errors::NotesError;
+use crate::notes::NoteWithId;
+use crate::util::config_dir;
+
+/// The value of a template field. Times are shown in the display time format
+/// unless a `date` filter renders the stored time in another.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Time { stored: String, shown: String },
+    List(Vec<String>),
+}
+
+impl Value {
+    fn of(column: Column, note: &NoteWithId<'_>, time_format: &TimeFormat) -> Self {
+        let time = |stored: &String| Value::Time {
+            stored: stored.clone(),
+            shown: time_format.render(stored, chrono::Local::now()),
+        };
+
+        match column {
+            Column::Id => Value::Text(note.id().to_string()),
+            Column::Created => time(note.created()),
+            Column::Modified => time(note.modified()),
+            Column::Tags => Value::List(Vec::from(note.tags())),
+            Column::Title => Value::Text(note.title()),
+            Column::Content => Value::Text(note.content().clone()),
+            Column::Desc => Value::Text(note.desc().clone()),
+            Column::Contexts => Value::List(note.contexts().clone()),
+            Column::Projects => Value::List(note.projects().clone()),
+            Column::Length => Value::Text(note.content().chars().count().to_string()),
+            Column::Similarity => Value::Text(String::new()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Text(text) | Value::Time { stored: text, .. } => text.is_empty(),
+            Value::List(items) => items.is_empty(),
+        }
+    }
+
+    /// The value as text. Lists are joined by commas, as in tables.
+    fn into_text(self) -> String {
+        match self {
+            Value::Text(text) => text,
+            Value::Time { shown, .. } => shown,
+            Value::List(items) => items.join(","),
+        }
+    }
+
+    /// Applies a function to the text of a value, or to each item of a list.
+    fn map(self, f: impl Fn(&str) -> String) -> Self {
+        match self {
+            Value::List(items) => Value::List(items.iter().map(|item| f(item)).collect()),
+            value => Value::Text(f(&value.into_text())),
+        }
+    }
+}
+
+/// A filter applied to a field with `|`.
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// Cuts text to a display width, ending it with an ellipsis.
+    Truncate(usize),
+    Upper,
+    Lower,
+    /// Renders a time in another format, such as `date("relative")`.
+    Date(TimeFormat),
+    /// Joins a list with a separator.
+    Join(String),
+    /// Replaces an empty value.
+    Default(String),
+}
+
+impl Filter {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let invalid = |reason: &str| invalid_template(s, reason);
+
+        let (name, arg) = match s.find('(') {
+            Some(start) if s.ends_with(')') => (
+                s[..start].trim(),
+                Some(unquote(s[start + 1..s.len() - 1].trim())),
+            ),
+            Some(_) => return Err(invalid("a filter argument is missing its `)`")),
+            None => (s, None),
+        };
+
+        let filter = match (name, arg) {
+            ("upper", None) => Filter::Upper,
+            ("lower", None) => Filter::Lower,
+            ("truncate", Some(width)) => Filter::Truncate(
+                width
+                    .parse()
+                    .ok()
+                    .filter(|width| *width > 0)
+                    .ok_or_else(|| invalid("the width must be a positive number"))?,
+            ),
+            ("date", Some(format)) => Filter::Date(format.parse()?),
+            ("join", Some(separator)) => Filter::Join(separator),
+            ("default", Some(default)) => Filter::Default(default),
+            ("upper", _) | ("lower", _) => return Err(invalid("the filter takes no argument")),
+            ("truncate", None) | ("date", None) | ("join", None) | ("default", None) => {
+                return Err(invalid("the filter needs an argument"))
+            }
+            _ => {
+                return Err(invalid(
+                    "unknown filter; expected truncate, upper, lower, date, join or default",
+                ))
+            }
+        };
+
+        Ok(filter)
+    }
+
+    fn apply(&self, value: Value) -> Value {
+        match self {
+            Filter::Truncate(width) => {
+                Value::Text(console::truncate_str(&value.into_text(), *width, "…").into_owned())
+            }
+            Filter::Upper => value.map(str::to_uppercase),
+            Filter::Lower => value.map(str::to_lowercase),
+            Filter::Date(format) => match value {
+                Value::Time { stored, .. } => {
+                    Value::Text(format.render(&stored, chrono::Local::now()))
+                }
+                value => value,
+            },
+            Filter::Join(separator) => match value {
+                Value::List(items) => Value::Text(items.join(separator)),
+                value => value,
+            },
+            Filter::Default(default) if value.is_empty() => Value::Text(default.clone()),
+            Filter::Default(_) => value,
+        }
+    }
+}
+
+/// A field and the filters applied to it, such as `title|truncate(40)`.
+#[derive(Debug, Clone, PartialEq)]
+struct Expr {
+    field: Column,
+    filters: Vec<Filter>,
+}
+
+impl Expr {
+    fn parse(s: &str) -> anyhow::Result<Self> {
+        let mut parts = split_outside_quotes(s, '|').into_iter();
+        let field = parts.next().unwrap_or_default();
+
+        Ok(Expr {
+            field: field
+                .trim()
+                .parse()
+                .map_err(|_| invalid_template(s, "unknown field"))?,
+            filters: parts
+                .map(|filter| Filter::parse(filter.trim()))
+                .collect::<anyhow::Result<Vec<Filter>>>()?,
+        })
+    }
+
+    fn eval(&self, note: &NoteWithId<'_>, time_format: &TimeFormat) -> Value {
+        self.filters
+            .iter()
+            .fold(Value::of(self.field, note, time_format), |value, filter| {
+                filter.apply(value)
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Expr(Expr),
+    /// `{if field}...{else}...{end}`, taking the first branch if the field is
+    /// not empty, or if it is empty with `{if !field}`.
+    If {
+        negated: bool,
+        cond: Expr,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A template that each note is printed with, such as
+/// `{id}: {title|truncate(40)} [{tags|join(" ")}]`. Fields are written in
+/// braces and literal braces are doubled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses a template, or loads the template saved by name in the config
+    /// directory if it is given as `@name`.
+    pub fn load(s: &str) -> anyhow::Result<Self> {
+        match s.strip_prefix('@') {
+            Some(name) => {
+                let path = config_dir()
+                    .context("Unable to access config directory.")?
+                    .join("templates")
+                    .join(format!("{}.tmpl", name));
+                let template = fs::read_to_string(&path)
+                    .map_err(|_| Error::new(NotesError::TemplateNotFound(name.to_string())))?;
+
+                template.trim_end_matches('\n').parse()
+            }
+            None => s.parse(),
+        }
+    }
+
+    /// Renders a note with the template, showing times in a time format.
+    pub fn render(&self, note: &NoteWithId<'_>, time_format: &TimeFormat) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, note, time_format, &mut out);
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut tokens = tokenize(s)?.into_iter();
+
+        match parse_nodes(s, &mut tokens)? {
+            (nodes, None) => Ok(Template { nodes }),
+            (_, Some(tag)) => Err(invalid_template(
+                s,
+                &format!("`{{{}}}` outside of `{{if}}`", tag),
+            )),
+        }
+    }
+}
+
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+/// Splits a template into text and the tags written in braces.
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut text = String::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek().map(|(_, next)| *next) == Some(c) => {
+                chars.next();
+                text.push(c);
+            }
+            '}' => return Err(invalid_template(s, "unmatched `}`; write `}}` for a brace")),
+            '{' => {
+                let end = tag_end(s, i).ok_or_else(|| invalid_template(s, "unclosed `{`"))?;
+                while chars.peek().is_some_and(|(j, _)| *j <= end) {
+                    chars.next();
+                }
+
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Tag(s[i + 1..end].trim().to_string()));
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    Ok(tokens)
+}
+
+/// Returns the index of the `}` that closes the tag opened at `start`,
+/// skipping braces in quoted filter arguments.
+fn tag_end(s: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+
+    for (i, c) in s[start + 1..].char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '}') => return Some(start + 1 + i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parses nodes up to the end of the template, or up to an `{else}` or
+/// `{end}` tag, which is returned.
+fn parse_nodes(
+    s: &str,
+    tokens: &mut impl Iterator<Item = Token>,
+) -> anyhow::Result<(Vec<Node>, Option<String>)> {
+    let mut nodes = vec![];
+
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Token::Tag(tag) if tag == "else" || tag == "end" => return Ok((nodes, Some(tag))),
+            Token::Tag(tag) => tag,
+        };
+
+        let cond = match tag.strip_prefix("if ") {
+            Some(cond) => cond.trim(),
+            None => {
+                nodes.push(Node::Expr(Expr::parse(&tag)?));
+                continue;
+            }
+        };
+
+        let (negated, cond) = match cond.strip_prefix('!') {
+            Some(cond) => (true, cond),
+            None => (false, cond),
+        };
+        let unclosed = || invalid_template(s, "`{if}` without `{end}`");
+
+        let (then, end) = parse_nodes(s, tokens)?;
+        let otherwise = match end.as_deref() {
+            Some("end") => vec![],
+            Some("else") => match parse_nodes(s, tokens)? {
+                (otherwise, Some(end)) if end == "end" => otherwise,
+                _ => return Err(unclosed()),
+            },
+            _ => return Err(unclosed()),
+        };
+
+        nodes.push(Node::If {
+            negated,
+            cond: Expr::parse(cond)?,
+            then,
+            otherwise,
+        });
+    }
+
+    Ok((nodes, None))
+}
+
+fn render_nodes(nodes: &[Node], note: &NoteWithId<'_>, time_format: &TimeFormat, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Expr(expr) => out.push_str(&expr.eval(note, time_format).into_text()),
+            Node::If {
+                negated,
+                cond,
+                then,
+                otherwise,
+            } => {
+                if cond.eval(note, time_format).is_empty() == *negated {
+                    render_nodes(then, note, time_format, out);
+                } else {
+                    render_nodes(otherwise, note, time_format, out);
+                }
+            }
+        }
+    }
+}
+
+/// Splits on a separator that is not inside quotes.
+fn split_outside_quotes(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c == separator => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Removes the quotes around a filter argument, if it has them.
+fn unquote(s: &str) -> String {
+    for quote in &['"', '\''] {
+        if s.len() >= 2 && s.starts_with(*quote) && s.ends_with(*quote) {
+            return s[1..s.len() - 1].to_string();
+        }
+    }
+
+    s.to_string()
+}
+
+fn invalid_template(template: &str, reason: &str) -> Error {
+    Error::new(NotesError::InvalidTemplate(
+        template.to_string(),
+        reason.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::NoteBuilder;
+    use crate::tags::Tags;
+
+    #[test]
+    fn render_templates() -> anyhow::Result<()> {
+        let note = NoteBuilder::new()
+            .with_content("Deploy the staging server\nThen check the logs")
+            .with_tags(Tags::from(vec!["ops", "rust"]))
+            .build();
+        let note = NoteWithId(4, &note);
+        let render = |template: &str| -> anyhow::Result<String> {
+            Ok(template
+                .parse::<Template>()?
+                .render(&note, &TimeFormat::default()))
+        };
+
+        assert_eq!(
+            render(r#"{id}: {title|truncate(10)} [{tags|join(" ")}]"#)?,
+            "4: Deploy th… [ops rust]"
+        );
+        assert_eq!(render("{tags|upper} {{{length}}}")?, "OPS,RUST {45}");
+        assert_eq!(
+            render("{desc|default('-')}|{desc|upper|default(\"none\")}")?,
+            "-|none"
+        );
+        assert_eq!(
+            render("{if desc}{desc}{else}no desc{end}{if !projects} {tags}{end}")?,
+            "no desc ops,rust"
+        );
+        assert_eq!(render("{if tags}{if desc}x{else}y{end}{end}")?, "y");
+        assert_eq!(render(r#"{created|date("%Y")|lower}"#)?.len(), 4);
+        assert_eq!(render("{title|join('}')}")?, "Deploy the staging server");
+
+        for invalid in &[
+            "{colour}",
+            "{title|shout}",
+            "{title|truncate}",
+            "{title|truncate(x)}",
+            "{title|upper(1)}",
+            "{title",
+            "title}",
+            "{if desc}x",
+            "{else}",
+            "{if desc}x{end}{end}",
+            "{created|date(\"soon\")}",
+        ] {
+            assert!(invalid.parse::<Template>().is_err(), "{}", invalid);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use anyhow::Error;
+use console::Style;
+
+use crate::errors::NotesError;
+
+/// When to print colors, given by `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    /// Colors when printing to a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Enables or disables colors on stdout and stderr. When automatic, the
+    /// terminal detection of `console` decides, which also follows
+    /// `CLICOLOR` and `CLICOLOR_FORCE`.
+    pub fn apply(self) {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let enabled = |detected: bool| match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => detected && !no_color,
+        };
+
+        console::set_colors_enabled(enabled(console::colors_enabled()));
+        console::set_colors_enabled_stderr(enabled(console::colors_enabled_stderr()));
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(Error::new(NotesError::InvalidColorChoice(s.to_string()))),
+        }
+    }
+}
+
+/// The styles that tables are printed with. Tags with a registered color are
+/// printed in it rather than the tag style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub id: Style,
+    pub time: Style,
+    pub tag: Style,
+    pub header: Style,
+    pub highlight: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            id: Style::new().bold(),
+            time: Style::new().bold(),
+            tag: Style::new(),
+            header: Style::new().underlined(),
+            highlight: Style::new().yellow().bold(),
+        }
+    }
+}
+
+impl Theme {
+    /// Styles each line of some text separately, so tables can split cells
+    /// into lines without the style running into other cells.
+    pub fn paint(style: &Style, text: &str) -> String {
+        text.split('\n')
+            .map(|line| style.apply_to(line).to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Parses a dotted style such as `cyan.bold`, or `none` for no style.
+fn parse_style(s: &str) -> Option<Style> {
+    if s == "none" {
+        return Some(Style::new());
+    }
+
+    s.split('.')
+        .all(|part| Style::from_dotted_str(part) != Style::new())
+        .then(|| Style::from_dotted_str(s))
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    /// Parses comma separated `key=style` settings, such as
+    /// `id=cyan.bold,time=dim`, over the default theme.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut theme = Theme::default();
+
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let invalid = || Error::new(NotesError::InvalidTheme(setting.to_string()));
+            let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+            let style = parse_style(value.trim()).ok_or_else(invalid)?;
+
+            match key.trim() {
+                "id" => theme.id = style,
+                "time" => theme.time = style,
+                "tag" => theme.tag = style,
+                "header" => theme.header = style,
+                "highlight" => theme.highlight = style,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_theme() -> anyhow::Result<()> {
+        let theme: Theme = "id=cyan.bold, time=none,highlight=red".parse()?;
+
+        assert_eq!(theme.id, Style::new().cyan().bold());
+        assert_eq!(theme.time, Style::new());
+        assert_eq!(theme.highlight, Style::new().red());
+        assert_eq!(theme.header, Theme::default().header);
+
+        assert!("id=cyan.bold.sparkly".parse::<Theme>().is_err());
+        assert!("pinned=red".parse::<Theme>().is_err());
+        assert!("id".parse::<Theme>().is_err());
+
+        Ok(())
+    }
+}
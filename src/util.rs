@@ -1,7 +1,34 @@
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use anyhow::Error;
 use chrono::{DateTime, Local};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::NotesError;
+
+/// Returns the directory that configuration such as saved templates is read
+/// from: `NOTES_CONFIG_DIR` if it is set, or `notes` in the user's config
+/// directory.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("NOTES_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::config_dir().map(|dir| dir.join("notes")))
+}
+
+/// Writes a file by writing a temporary file next to it and moving that into
+/// place, so the file is never left half-written.
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &[u8]) -> anyhow::Result<()> {
+    let mut tmp = path.as_ref().as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+
+    Ok(())
+}
 
 pub fn file_is_dir<P: AsRef<Path>>(path: P) -> anyhow::Result<bool> {
     let metadata = fs::metadata(path)?;
@@ -22,3 +49,35 @@ pub fn get_file_contents<P: AsRef<Path>>(path: P) -> anyhow::Result<String> {
     let contents = fs::read_to_string(&path)?;
     Ok(contents.trim().to_string())
 }
+
+/// Reads a file stored next to the notes file, or returns the default value
+/// if it does not exist yet.
+pub fn read_sidecar<T, P>(path: P, err: NotesError) -> anyhow::Result<T>
+where
+    T: DeserializeOwned + Default,
+    P: AsRef<Path>,
+{
+    if !path.as_ref().exists() {
+        return Ok(T::default());
+    }
+
+    let buf = fs::read(path)?;
+
+    bincode::deserialize::<T>(&buf).map_err(|_| Error::new(err))
+}
+
+/// Writes a file stored next to the notes file. Nothing is written for an
+/// empty value unless the file already exists.
+pub fn write_sidecar<T, P>(value: &T, is_empty: bool, path: P) -> anyhow::Result<()>
+where
+    T: Serialize,
+    P: AsRef<Path>,
+{
+    if is_empty && !path.as_ref().exists() {
+        return Ok(());
+    }
+
+    let serialized = bincode::serialize(value)?;
+
+    write_atomic(path, serialized.as_slice())
+}
@@ -1,8 +1,6 @@
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
 
-use assert_cmd::prelude::*;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use tempfile::{tempdir, TempDir};
@@ -22,6 +20,7 @@ struct TestCommand {
     _tempdir: TempDir,
 }
 
+#[allow(dead_code)]
 impl TestCommand {
     pub fn new() -> anyhow::Result<Self> {
         let tempdir = tempdir()?;
@@ -46,6 +45,7 @@ impl TestCommand {
 
 macro_rules! cmd_with_args {
     ($cmd:ident, [$($arg:expr),*]) => {{
+        #[allow(unused_mut)]
         let mut new_cmd = new_cmd!($cmd.path);
 
         $(
@@ -60,6 +60,7 @@ macro_rules! cmd_with_args {
         cmd_with_args!($cmd, [])
     };
     ($($arg:expr),*) => {{
+        #[allow(unused_mut)]
         let mut cmd = TestCommand::new()?;
 
         $(
@@ -189,6 +190,1080 @@ fn encrypt_notes() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn list_tags() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("tags");
+    assert_success!(cmd, predicate::str::contains("There are no tags."));
+
+    cmd = cmd_with_args!(cmd, ["new", "first", "--tags", "rust", "cli"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "second", "--tags", "rust"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"rust\s+2")?.and(predicate::str::is_match(r"cli\s+1")?)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rename_tag() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "first", "--tags", "k8s");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["tags", "rename", "k8s", "kubernetes"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Tag `k8s` renamed to `kubernetes` on 1 note(s).")
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("kubernetes").and(predicate::str::contains("k8s").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags", "rename", "k8s", "kubernetes"]);
+    cmd.cmd.assert().failure().stderr(predicate::str::contains(
+        "Tag `k8s` is not used by any note.",
+    ));
+
+    cmd = cmd_with_args!(cmd, ["new", "second", "--tags", "kube"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "tags",
+            "set",
+            "kube",
+            "--desc",
+            "Containers.",
+            "--aliases",
+            "kb"
+        ]
+    );
+    cmd.cmd.assert().success();
+
+    cmd = cmd_with_args!(cmd, ["tags", "set", "kubernetes", "--aliases", "k8s"]);
+    cmd.cmd.assert().success();
+
+    cmd = cmd_with_args!(cmd, ["tags", "rename", "kube", "kubernetes"]);
+    cmd.cmd.assert().success();
+
+    cmd = cmd_with_args!(cmd, ["tags", "--registered"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"kubernetes\s+k8s,kb\s+Containers\.")?
+            .and(predicate::str::contains("kube ").not())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn merge_tags() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "first", "--tags", "a", "b");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "second", "--tags", "b"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["tags", "merge", "a", "b", "--into", "c"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Tags `a,b` merged into `c` on 2 note(s).")
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(cmd, predicate::str::is_match(r"c\s+2")?);
+
+    Ok(())
+}
+
+#[test]
+fn register_tag_with_alias() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "first", "--tags", "k8s");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "second", "--tags", "kubernetes"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "tags",
+            "set",
+            "kubernetes",
+            "--aliases",
+            "k8s",
+            "--color",
+            "blue",
+            "--desc",
+            "Container orchestration."
+        ]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::contains("Tag `kubernetes` registered.")
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"kubernetes\s+2")?
+            .and(predicate::str::contains("Container orchestration."))
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags", "--registered"]);
+    assert_success!(cmd, predicate::str::is_match(r"kubernetes\s+blue\s+k8s")?);
+
+    cmd = cmd_with_args!(cmd, ["tags", "set", "other", "--color", "purple"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("`purple` is not a color"));
+
+    Ok(())
+}
+
+#[test]
+fn auto_tag_rules() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "fix JIRA-12 first");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["rules", "add", "--content", r"JIRA-\d+", "ticket"]);
+    assert_success!(cmd, predicate::str::contains("Rule with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "then JIRA-13"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Tags `ticket` added by auto-tagging rules.")
+    );
+
+    cmd = cmd_with_args!(cmd, ["retag", "--dry-run"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"0\s+fix JIRA-12 first\s+ticket")?
+            .and(predicate::str::contains("Dry run; no notes were changed."))
+    );
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(cmd, predicate::str::is_match(r"ticket\s+1")?);
+
+    cmd = cmd_with_args!(cmd, ["retag"]);
+    assert_success!(cmd, predicate::str::contains("1 note(s) retagged."));
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(cmd, predicate::str::is_match(r"ticket\s+2")?);
+
+    cmd = cmd_with_args!(cmd, ["edit", "1", "--tags", "later"]);
+    assert_success!(cmd, predicate::str::contains("Note 1 edited: then JIRA-13"));
+
+    cmd = cmd_with_args!(cmd, ["tags"]);
+    assert_success!(cmd, predicate::str::is_match(r"ticket\s+1")?);
+
+    Ok(())
+}
+
+#[test]
+fn auto_tag_file_extension() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("rules", "add", "--ext", "sql", "sql");
+    assert_success!(cmd, predicate::str::contains("Rule with ID 0 created."));
+
+    let file = cmd.path().with_file_name("query.sql");
+    std::fs::write(&file, "select 1;")?;
+
+    cmd = cmd_with_args!(cmd, ["new", "--file", file.as_os_str()]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Tags `sql` added by auto-tagging rules.")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn inline_hashtags() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "fix the #deploy script for #staging");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "0"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("deploy,staging").and(predicate::str::contains(
+            "fix the #deploy script for #staging"
+        ))
+    );
+
+    cmd = cmd_with_args!(
+        cmd,
+        ["new", "--strip-markers", "call about #billing @phone"]
+    );
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "1"]);
+    assert_success!(cmd, predicate::str::is_match(r"billing\s+call about\n")?);
+
+    Ok(())
+}
+
+#[test]
+fn search_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "lunch with sam", "--desc", "talk about deploy");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "Deploy checklist", "--tags", "ops"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "nothing here"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    // The title match ranks above the description match.
+    cmd = cmd_with_args!(cmd, ["search", "DEPLOY"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"(?s)Deploy checklist.*lunch with sam")?
+            .and(predicate::str::contains("nothing here").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy", "--in", "content,tags"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy checklist")
+            .and(predicate::str::contains("lunch with sam").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "missing"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    Ok(())
+}
+
+#[test]
+fn search_notes_with_regex_and_fuzzy() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy checklist", "--desc", "ticket OPS-123");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "lunch with sam"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["search", "--regex", r"ops-\d+", "--field", "desc"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy checklist")
+            .and(predicate::str::contains("lunch with sam").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "--regex", r"ops-\d+", "--field", "content"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    cmd = cmd_with_args!(cmd, ["search", "--fuzzy", "deplyo", "luch"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    cmd = cmd_with_args!(cmd, ["search", "--fuzzy", "deplyo", "chekclist"]);
+    assert_success!(cmd, predicate::str::contains("Deploy checklist"));
+
+    Ok(())
+}
+
+#[test]
+fn filter_notes_by_date() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "deploy today");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--since", "7d"]);
+    assert_success!(cmd, predicate::str::contains("deploy today"));
+
+    cmd = cmd_with_args!(cmd, ["get", "--until", "yesterday"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--on", "today", "--modified"]);
+    assert_success!(cmd, predicate::str::contains("deploy today"));
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy", "--on", "2020-01-01"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--since", "someday"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("`someday` is not a date"));
+
+    Ok(())
+}
+
+#[test]
+fn sort_and_limit_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "a medium note", "--tags", "b");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "the longest note of all"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "a note", "--tags", "a"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--sort", "length", "--reverse"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"(?s)the longest note of all.*a medium note.*a note")?
+    );
+
+    cmd = cmd_with_args!(
+        cmd,
+        ["get", "--sort", "tag", "--limit", "1", "--offset", "1"]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::contains("a medium note")
+            .and(predicate::str::contains("a note ").not())
+            .and(predicate::str::contains("longest").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "note", "--sort", "id", "-n", "2"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"(?s)a medium note.*the longest note")?
+            .and(predicate::str::contains("a note ").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "--offset", "3"]);
+    assert_success!(cmd, predicate::str::contains("There are no notes."));
+
+    Ok(())
+}
+
+#[test]
+fn query_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy staging", "--tags", "rust");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "Deploy done", "--tags", "rust", "done"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "lunch"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "get",
+            r#"tag:rust created>2020-01-01 content~"^deploy" -tag:done"#
+        ]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy staging")
+            .and(predicate::str::contains("Deploy done").not())
+            .and(predicate::str::contains("lunch").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "--", "-tag:rust"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("lunch").and(predicate::str::contains("Deploy").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "tag:rust", "-tag:done"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy staging")
+            .and(predicate::str::contains("Deploy done").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "-tag:rust", "-o", "csv"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("lunch").and(predicate::str::contains("Deploy").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "tag:rust", "--colums", "id"]);
+    cmd.cmd.assert().failure().stderr(predicate::str::contains(
+        "Found argument `--colums` which wasn't expected",
+    ));
+
+    cmd = cmd_with_args!(cmd, ["get", "--tags", "done"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy done").and(predicate::str::contains("staging").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "id>5"]);
+    assert_success!(cmd, predicate::str::contains("No notes found."));
+
+    cmd = cmd_with_args!(cmd, ["get", "tga:rust"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid query term `tga:rust`"));
+
+    Ok(())
+}
+
+#[test]
+fn saved_queries() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "pager fired", "--tags", "oncall");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "pager resolved", "--tags", "oncall", "done"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["query"]);
+    assert_success!(cmd, predicate::str::contains("There are no saved queries."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "query",
+            "save",
+            "oncall",
+            "tag:oncall",
+            "created>7d",
+            "-tag:done"
+        ]
+    );
+    assert_success!(cmd, predicate::str::contains("Query `oncall` saved."));
+
+    cmd = cmd_with_args!(cmd, ["query", "list"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"oncall\s+tag:oncall created>7d -tag:done\s+1")?
+    );
+
+    cmd = cmd_with_args!(cmd, ["query", "run", "oncall"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("pager fired").and(predicate::str::contains("resolved").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "@oncall"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("pager fired").and(predicate::str::contains("resolved").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "@missing"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No query is saved as `missing`."));
+
+    Ok(())
+}
+
+#[test]
+fn related_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy the staging server", "--tags", "ops");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "Lunch with sam"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "The staging deploy failed", "--tags", "ops"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "Server maintenance"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 3 created."));
+
+    cmd = cmd_with_args!(cmd, ["related", "0"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"(?s)staging deploy failed.*Server maintenance")?
+            .and(predicate::str::contains("Lunch").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["related", "0", "-n", "1"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("staging deploy failed")
+            .and(predicate::str::contains("maintenance").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["related", "1"]);
+    assert_success!(cmd, predicate::str::contains("No related notes found."));
+
+    Ok(())
+}
+
+#[test]
+fn list_duplicates() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Call the bank about the mortgage rate");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "lunch with sam"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "call the bank about the mortgage rate!"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(cmd, ["dedupe", "--dry-run"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Duplicate group 1 of 1:")
+            .and(predicate::str::contains("mortgage rate!"))
+            .and(predicate::str::contains("lunch").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["dedupe", "--dry-run", "--threshold", "1.5"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("from 0 to 1"));
+
+    Ok(())
+}
+
+#[test]
+fn machine_readable_output() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy, then \"verify\"", "--tags", "ops", "rust");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--output", "json"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains(r#""id": 0"#)
+            .and(predicate::str::contains(
+                r#""content": "Deploy, then \"verify\"""#
+            ))
+            .and(predicate::str::is_match(
+                r#""tags": \[\s*"ops",\s*"rust"\s*\]"#
+            )?)
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy", "-o", "ndjson"]);
+    assert_success!(cmd, predicate::str::starts_with(r#"{"id":0,"created":"#));
+
+    cmd = cmd_with_args!(cmd, ["get", "-o", "csv"]);
+    assert_success!(
+        cmd,
+        predicate::str::starts_with("id,created,modified,tags,content,desc,contexts,projects\n")
+            .and(predicate::str::contains(
+                r#""ops,rust","Deploy, then ""verify""""#
+            ))
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "tag:missing", "-o", "json"]);
+    assert_success!(cmd, predicate::str::similar("[]\n"));
+
+    cmd = cmd_with_args!(cmd, ["get", "tag:missing", "-o", "tsv"]);
+    assert_success!(
+        cmd,
+        predicate::str::similar("id\tcreated\tmodified\ttags\tcontent\tdesc\tcontexts\tprojects\n")
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "tga:rust", "-o", "ndjson"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::starts_with(
+            r#"{"error":"Invalid query term"#,
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn select_columns() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy checklist\nStep one", "--tags", "ops");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--columns", "id,tags,title,length"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"Id\s+Tags\s+Title\s+Length\n")?
+            .and(predicate::str::is_match(
+                r"0\s+ops\s+Deploy checklist\s+25"
+            )?)
+            .and(predicate::str::contains("Created").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy", "--columns", "title", "-d"]);
+    assert_success!(cmd, predicate::str::is_match(r"^Title\s+Desc")?);
+
+    cmd = cmd_with_args!(cmd, ["get"]);
+    cmd.cmd.env("NOTES_COLUMNS", "content,id");
+    assert_success!(cmd, predicate::str::is_match(r"^Content\s+Id")?);
+
+    cmd = cmd_with_args!(cmd, ["get", "--columns", "id,colour"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown column `colour`"));
+
+    Ok(())
+}
+
+#[test]
+fn fit_tables_to_terminal() -> anyhow::Result<()> {
+    let content = "日本語のメモ and a rather long note that does not fit in a narrow terminal";
+    let mut cmd = cmd_with_args!("new", content);
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--columns", "id,content"]);
+    cmd.cmd.env("COLUMNS", "24");
+    assert_success!(cmd, predicate::str::contains("0  日本語のメモ and a r…\n"));
+
+    cmd = cmd_with_args!(cmd, ["get", "--columns", "id,content", "--no-truncate"]);
+    cmd.cmd.env("COLUMNS", "24");
+    assert_success!(cmd, predicate::str::contains(content));
+
+    cmd = cmd_with_args!(cmd, ["get", "--columns", "id,content"]);
+    cmd.cmd.env_remove("COLUMNS");
+    assert_success!(cmd, predicate::str::contains(content));
+
+    Ok(())
+}
+
+#[test]
+fn color_output() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy the server");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy"]);
+    cmd.cmd.env_remove("CLICOLOR_FORCE");
+    assert_success!(cmd, predicate::str::contains("\u{1b}").not());
+
+    let path = cmd.path().to_owned();
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.arg("--color").arg("always").arg("--path").arg(&path);
+    cmd.args(["search", "deploy"]);
+    cmd.assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\u{1b}[1m0\u{1b}[0m").and(predicate::str::contains(
+                "\u{1b}[33m\u{1b}[1mDeploy\u{1b}[0m",
+            )),
+        );
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--color", "always", "--theme", "id=cyan,highlight=none"]);
+    cmd.arg("--path").arg(&path).args(["search", "deploy"]);
+    cmd.assert().success().stdout(
+        predicate::str::contains("\u{1b}[36m0\u{1b}[0m")
+            .and(predicate::str::contains(" Deploy the server")),
+    );
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--color", "never", "--path"]).arg(&path);
+    cmd.env("CLICOLOR_FORCE", "1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}").not());
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.arg("--path")
+        .arg(&path)
+        .args(["get", "0", "--no-pager"]);
+    cmd.args([
+        "--color",
+        "always",
+        "--theme",
+        "id=cyan",
+        "--time-format",
+        "iso",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout(
+            predicate::str::contains("\u{1b}[36m0\u{1b}[0m").and(predicate::str::is_match(
+                r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}[+-]\d{2}:\d{2}",
+            )?),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn time_formats() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy the server");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    let path = cmd.path().to_owned();
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--time-format", "relative", "--path"]).arg(&path);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"0\s+just now\s+Deploy the server",
+    )?);
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.arg("--path").arg(&path).env("NOTES_TIME_FORMAT", "iso");
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r"0\s+\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}[+-]\d{2}:\d{2}\s+Deploy",
+    )?);
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--time-format", "%Y", "--path"]).arg(&path);
+    cmd.args(["get", "-o", "json"]);
+    cmd.assert().success().stdout(predicate::str::is_match(
+        r#""created": "\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}""#,
+    )?);
+
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--time-format", "soon", "--path"]).arg(&path);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("`soon` is not a time format"));
+
+    Ok(())
+}
+
+#[test]
+fn format_notes_with_template() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!(
+        "new",
+        "Deploy the staging server tonight",
+        "--tags",
+        "ops",
+        "rust"
+    );
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["new", "Lunch"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "get",
+            "--format",
+            r#"{id}: {title|truncate(12)|upper}{if tags} [{tags|join(" ")}]{else} -{end}"#
+        ]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::similar("0: DEPLOY THE … [ops rust]\n1: LUNCH -\n")
+    );
+
+    let config = tempdir()?;
+    std::fs::create_dir(config.path().join("templates"))?;
+    std::fs::write(
+        config.path().join("templates").join("bar.tmpl"),
+        "{id} {desc|default(\"no desc\")}\n",
+    )?;
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy", "--format", "@bar"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::similar("0 no desc\n"));
+
+    cmd = cmd_with_args!(cmd, ["get", "--format", "@missing"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    cmd.cmd.assert().failure().stderr(predicate::str::contains(
+        "No template is saved as `missing`.",
+    ));
+
+    cmd = cmd_with_args!(cmd, ["get", "--format", "{title|shout}"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown filter"));
+
+    Ok(())
+}
+
+#[test]
+fn show_note() -> anyhow::Result<()> {
+    let content = "# Deploy plan\n\nRun **staging** first.\n\n- check `logs`\n- ping ops\n\n> careful\n\n```sh\nmake deploy\n```";
+    let mut cmd = cmd_with_args!("new", content, "--tags", "ops", "--desc", "Tonight");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["show", "0"]);
+    assert_success!(
+        cmd,
+        predicate::str::starts_with("Note 0\nCreated ")
+            .and(predicate::str::contains("Tags     ops\nDesc     Tonight\n"))
+            .and(predicate::str::contains("Contexts").not())
+            .and(predicate::str::contains(
+                "# Deploy plan\n\nRun staging first.\n\n• check logs\n• ping ops\n\n│ careful\n\n    make deploy\n"
+            ))
+            .and(predicate::str::contains("\u{1b}").not())
+    );
+
+    cmd = cmd_with_args!(cmd, ["show", "0", "--format", "{id} {desc}"]);
+    assert_success!(cmd, predicate::str::similar("0 Tonight\n"));
+
+    cmd = cmd_with_args!(cmd, ["show", "3"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Note ID `3` is invalid."));
+
+    Ok(())
+}
+
+#[test]
+fn pager_output() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy the server", "--tags", "ops");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    // Output is only paged on a terminal.
+    cmd = cmd_with_args!(cmd, ["get", "--all"]);
+    cmd.cmd.env("PAGER", "sed s/^/paged:/");
+    assert_success!(
+        cmd,
+        predicate::str::contains("Deploy the server").and(predicate::str::contains("paged:").not())
+    );
+
+    let path = cmd.path().to_owned();
+    let mut cmd = Command::cargo_bin("notes")?;
+    cmd.args(["--no-pager", "--color", "always", "--path"])
+        .arg(&path);
+    cmd.arg("tags");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\u{1b}[1mops\u{1b}[0m"));
+
+    Ok(())
+}
+
+#[test]
+fn group_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy", "--tags", "ops", "rust");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+    cmd = cmd_with_args!(cmd, ["new", "Plain"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+    cmd = cmd_with_args!(cmd, ["new", "Cargo", "--tags", "rust"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(
+        cmd,
+        [
+            "get",
+            "--all",
+            "--group-by",
+            "tag",
+            "--columns",
+            "id,content"
+        ]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::similar(
+            "ops (1)\nId Content\n0  Deploy\n\n\
+             rust (2)\nId Content\n0  Deploy\n2  Cargo\n\n\
+             (untagged) (1)\nId Content\n1  Plain\n"
+        )
+    );
+
+    cmd = cmd_with_args!(
+        cmd,
+        ["get", "--all", "--group-by", "month", "--format", "{id}"]
+    );
+    assert_success!(
+        cmd,
+        predicate::str::is_match(r"^\d{4}-\d{2} \(3\)\n0\n1\n2\n$")?
+    );
+
+    cmd = cmd_with_args!(cmd, ["get", "tag:none", "--group-by", "day"]);
+    assert_success!(cmd, predicate::str::similar("No notes found.\n"));
+
+    cmd = cmd_with_args!(cmd, ["get", "--all", "--group-by", "status"]);
+    cmd.cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn activity_and_timeline() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy the server\nwith care");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["activity", "--year", "1999"]);
+    assert_success!(
+        cmd,
+        predicate::str::starts_with("    Jan ")
+            .and(predicate::str::contains("\nMon "))
+            .and(predicate::str::contains("0 creations and edits in 1999."))
+    );
+
+    cmd = cmd_with_args!(cmd, ["activity"]);
+    assert_success!(cmd, predicate::str::contains("█"));
+
+    cmd = cmd_with_args!(cmd, ["activity", "--year", "262143"]);
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Year `262143` is out of range."));
+
+    cmd = cmd_with_args!(cmd, ["timeline"]);
+    assert_success!(
+        cmd,
+        predicate::str::is_match(
+            r"^\w{3} \d{4}-\d{2}-\d{2} \(1\)\n\d{2}:\d{2} created 0 Deploy the server\n$"
+        )?
+    );
+
+    cmd = cmd_with_args!(cmd, ["timeline", "--since", "2999-01-01"]);
+    assert_success!(cmd, predicate::str::similar("No activity found.\n"));
+
+    Ok(())
+}
+
+#[test]
+fn note_stats() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploy", "--tags", "ops", "rust");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+    cmd = cmd_with_args!(cmd, ["new", "Cargo build", "--tags", "rust"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+    cmd = cmd_with_args!(cmd, ["new", "Plain"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 2 created."));
+
+    cmd = cmd_with_args!(cmd, ["stats"]);
+    assert_success!(
+        cmd,
+        predicate::str::starts_with("Notes          3\nUntagged       1\n")
+            .and(predicate::str::contains("Average length 7 chars\n"))
+            .and(predicate::str::contains(
+                "Longest        note 1 (11 chars)\n"
+            ))
+            .and(predicate::str::contains("Per week       3.0\n"))
+            .and(predicate::str::contains("\nTag  Notes\nrust 2\nops  1\n"))
+    );
+
+    cmd = cmd_with_args!(cmd, ["stats", "--output", "json"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("\"notes\": 3,")
+            .and(predicate::str::contains(
+                "\"tag\": \"rust\",\n      \"notes\": 2"
+            ))
+            .and(predicate::str::contains("\"store_size\": "))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn config_defaults() -> anyhow::Result<()> {
+    let config = tempdir()?;
+
+    let mut cmd = cmd_with_args!("config", "set", "columns", "id,title");
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(
+        cmd,
+        predicate::str::similar("`columns` set to `id,title`.\n")
+    );
+
+    for (key, value) in [("tags", "inbox"), ("confirm", "false")] {
+        cmd = cmd_with_args!(cmd, ["config", "set", key, value]);
+        cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+        assert_success!(cmd, predicate::str::contains("set to"));
+    }
+
+    cmd = cmd_with_args!(cmd, ["config", "set", "sort", "size"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Cannot sort by `size`"));
+
+    cmd = cmd_with_args!(cmd, ["config", "list"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(
+        cmd,
+        predicate::str::similar("columns id,title\nconfirm false\ntags    inbox\n")
+    );
+
+    cmd = cmd_with_args!(cmd, ["new", "Hello there"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    cmd = cmd_with_args!(cmd, ["get", "--all"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::similar("Id Title\n0  Hello there\n"));
+
+    // Arguments and environment variables override the config file.
+    cmd = cmd_with_args!(cmd, ["get", "--all"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    cmd.cmd.env("NOTES_COLUMNS", "id,tags");
+    assert_success!(cmd, predicate::str::similar("Id Tags\n0  inbox\n"));
+
+    cmd = cmd_with_args!(cmd, ["get", "--all", "--columns", "content"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::similar("Content\nHello there\n"));
+
+    cmd = cmd_with_args!(cmd, ["delete", "0"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(
+        cmd,
+        predicate::str::similar("Note `0: Hello there` deleted.\n")
+    );
+
+    cmd = cmd_with_args!(cmd, ["config", "get", "editor"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::similar("`editor` is not set.\n"));
+
+    let file = config.path().join("config.toml");
+    std::fs::write(&file, "color = \"purple\"\n")?;
+    cmd = cmd_with_args!(cmd, ["config", "set", "color", "never"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    assert_success!(cmd, predicate::str::contains("`color` set to `never`."));
+
+    std::fs::write(&file, "colour = \"never\"\n")?;
+    cmd = cmd_with_args!(cmd, ["get", "-a"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    cmd.cmd
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Using the default settings."));
+
+    cmd = cmd_with_args!(cmd, ["config", "set", "color", "never"]);
+    cmd.cmd.env("NOTES_CONFIG_DIR", config.path());
+    cmd.cmd
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("fix it by hand"));
+
+    Ok(())
+}
+
+#[test]
+fn reindex_notes() -> anyhow::Result<()> {
+    let mut cmd = cmd_with_args!("new", "Deploying the checklist");
+    assert_success!(cmd, predicate::str::contains("Note with ID 0 created."));
+
+    std::fs::remove_file(cmd.path().with_extension("index"))?;
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy"]);
+    cmd.cmd
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deploying the checklist"))
+        .stderr(predicate::str::contains("run `notes reindex`"));
+
+    cmd = cmd_with_args!(cmd, ["reindex"]);
+    assert_success!(
+        cmd,
+        predicate::str::contains("Search index rebuilt with 1 note(s).")
+    );
+
+    cmd = cmd_with_args!(cmd, ["search", "deploy"]);
+    cmd.cmd
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deploying the checklist"))
+        .stderr(predicate::str::is_empty());
+
+    std::fs::remove_file(cmd.path().with_extension("index"))?;
+
+    cmd = cmd_with_args!(cmd, ["new", "Staging checklist"]);
+    assert_success!(cmd, predicate::str::contains("Note with ID 1 created."));
+
+    cmd = cmd_with_args!(cmd, ["search", "checklist"]);
+    cmd.cmd
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Staging checklist"))
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
 // Tests to add:
 // - Deleting a note.
 // - Getting a note.